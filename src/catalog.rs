@@ -0,0 +1,142 @@
+use crate::location::Location;
+use crate::severity::SeverityLevel;
+use crate::ulog_message::ULogMessage;
+use crate::ulog_system_info::ULogSystemInfo;
+use crate::util::glob_match;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Listing of the messages and strings of a system, sorted by id
+#[derive(Debug, Clone, Serialize)]
+pub struct Catalog {
+    pub system_id: u16,
+    pub messages: Vec<CatalogMessage>,
+    pub strings: Vec<CatalogString>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CatalogMessage {
+    pub id: u16,
+    pub severity: SeverityLevel,
+    #[serde(flatten)]
+    pub location: Location,
+    /// Format string as written in the source, annotations included
+    pub format: String,
+    /// Name of the type of each argument, see [`crate::ulog_argument::ULogArgument::type_name`]
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CatalogString {
+    pub id: u16,
+    pub string: Arc<String>,
+    #[serde(flatten)]
+    pub location: Location,
+}
+
+/// Restricts the entries listed in a catalog, an empty filter lets everything through
+#[derive(Debug, Clone, Default)]
+pub struct CatalogFilter {
+    /// Only list messages with one of these severity levels
+    pub severities: Vec<SeverityLevel>,
+    /// Only list messages and strings declared in a file matching this glob
+    pub file: Option<String>,
+}
+
+impl CatalogFilter {
+    fn matches_location(&self, location: &Location) -> bool {
+        self.file
+            .as_ref()
+            .is_none_or(|x| glob_match(x, &location.file))
+    }
+
+    fn matches_message(&self, message: &ULogMessage) -> bool {
+        (self.severities.is_empty() || self.severities.contains(&message.severity_level()))
+            && self.matches_location(message.location())
+    }
+}
+
+impl Catalog {
+    /// Lists the messages and strings of a system that pass a filter. Strings have no severity,
+    /// they are only filtered by file
+    pub fn from_system(system: &ULogSystemInfo, filter: &CatalogFilter) -> Self {
+        let mut messages: Vec<_> = system
+            .messages()
+            .values()
+            .filter(|x| filter.matches_message(x))
+            .map(|x| CatalogMessage {
+                id: x.id(),
+                severity: x.severity_level(),
+                location: x.location().clone(),
+                format: x.format().to_string(),
+                arguments: x.arguments().iter().map(|x| x.type_name()).collect(),
+            })
+            .collect();
+        messages.sort_by_key(|x| x.id);
+
+        let mut strings: Vec<_> = system
+            .ulog_strings()
+            .values()
+            .filter(|x| filter.matches_location(x.location()))
+            .map(|x| CatalogString {
+                id: x.id(),
+                string: x.string().clone(),
+                location: x.location().clone(),
+            })
+            .collect();
+        strings.sort_by_key(|x| x.id);
+
+        Self {
+            system_id: system.system_id(),
+            messages,
+            strings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::catalog::{Catalog, CatalogFilter};
+    use crate::severity::SeverityLevel;
+    use crate::test_support::{SystemBuilder, location};
+
+    #[test]
+    fn filter() {
+        let system = SystemBuilder::new(0x0102)
+            .message_at(
+                2,
+                "{}",
+                &[251],
+                location("src/uart.c", 1),
+                SeverityLevel::Error,
+            )
+            .message_at(
+                1,
+                "{}",
+                &[251],
+                location("src/uart.c", 1),
+                SeverityLevel::Info,
+            )
+            .message_at(
+                0,
+                "{}",
+                &[251],
+                location("src/motor.c", 1),
+                SeverityLevel::Error,
+            )
+            .build();
+
+        let catalog = Catalog::from_system(&system, &CatalogFilter::default());
+        let ids: Vec<_> = catalog.messages.iter().map(|x| x.id).collect();
+        assert_eq!(ids, [0, 1, 2]);
+        assert_eq!(catalog.messages[0].arguments, ["u32"]);
+
+        let filter = CatalogFilter {
+            severities: vec![SeverityLevel::Error],
+            file: Some("*UART.c".to_string()),
+        };
+        let catalog = Catalog::from_system(&system, &filter);
+        let ids: Vec<_> = catalog.messages.iter().map(|x| x.id).collect();
+        assert_eq!(ids, [2]);
+    }
+}
@@ -0,0 +1,52 @@
+use crate::frame_header::DeviceTimestamp;
+use crate::location::Location;
+use crate::severity::SeverityLevel;
+use crate::timestamp::ReceiveTimestamp;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// A single log entry decoded from a frame
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedEntry {
+    /// Id of the system that emitted this entry
+    pub system_id: u16,
+    /// Id of the message definition within that system
+    pub message_id: u16,
+    /// Severity of this entry
+    #[serde(rename = "severity")]
+    pub severity_level: SeverityLevel,
+    /// Location where the message was defined
+    #[serde(flatten)]
+    pub location: Location,
+    /// Format string of the message, before any argument is formatted in
+    pub format: String,
+    /// Message with all of its arguments formatted in
+    pub text: String,
+    /// Values of the message's arguments, in order
+    pub arguments: Vec<ArgumentValue>,
+    /// Host time at which the frame was received, when it was read from a stream
+    pub received: Option<ReceiveTimestamp>,
+    /// Device time at which the entry was emitted, when the system sends a tick counter
+    pub device_time: Option<DeviceTimestamp>,
+    /// Number of entries of the system lost right before this one, when the system sends a
+    /// sequence counter
+    pub lost: u32,
+    /// Tag of the stream the entry was read from, when a source has several streams
+    pub source: Option<Arc<str>>,
+}
+
+/// Typed value of a decoded argument. Integers of every size are widened to 64 bits
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum ArgumentValue {
+    Slice(Vec<u8>),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Bool(bool),
+    #[serde(rename = "ulog_string")]
+    ULogString(Arc<String>),
+    Int(i64),
+    #[serde(rename = "uint")]
+    UInt(u64),
+}
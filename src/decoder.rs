@@ -0,0 +1,724 @@
+use crate::decoded_entry::DecodedEntry;
+use crate::dictionary::{DictionaryError, load_dictionary};
+use crate::elf::{ElfParseError, attempt_load_elf};
+use crate::endianness::Endianness;
+use crate::frame_crc::FrameCrc;
+use crate::frame_header::{DeviceClock, FrameHeaderExtension, SequenceTracker};
+use crate::frame_reader::{FrameReader, RawFrame};
+use crate::framing::Framing;
+use crate::lint::{LintIssue, lint_system};
+use crate::timestamp::ReceiveTimestamp;
+use crate::ulog_argument::ULogArgument;
+use crate::ulog_message::{ULogMessage, ULogMessageFormatError};
+use crate::ulog_system_info::ULogSystemInfo;
+use serde::Serialize;
+use snafu::{Backtrace, OptionExt, ResultExt, Snafu, ensure};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, Read};
+use std::path::Path;
+use std::sync::Arc;
+
+#[derive(Snafu, Debug)]
+pub enum SystemLoadError {
+    #[snafu(display("Failed to parse ELF file"))]
+    Elf {
+        #[snafu(backtrace)]
+        #[snafu(source(from(ElfParseError, Box::new)))]
+        source: Box<ElfParseError>,
+    },
+    #[snafu(display("Failed to load dictionary"))]
+    Dictionary {
+        #[snafu(backtrace)]
+        #[snafu(source(from(DictionaryError, Box::new)))]
+        source: Box<DictionaryError>,
+    },
+    #[snafu(display("The system id {system_id:x} is already in use"))]
+    DuplicateSystemId {
+        backtrace: Backtrace,
+        system_id: u16,
+    },
+    #[snafu(display(
+        "The map has {} lint issues:\n{}",
+        issues.len(),
+        issues.iter().map(|x| format!("  {x}")).collect::<Vec<_>>().join("\n")
+    ))]
+    Lint {
+        backtrace: Backtrace,
+        issues: Vec<LintIssue>,
+    },
+}
+
+#[derive(Snafu, Debug)]
+pub enum DecodeError {
+    #[snafu(display("Failed to read entry"))]
+    EntryRead {
+        backtrace: Backtrace,
+        source: std::io::Error,
+    },
+    #[snafu(display("Failed to decode {framing} frame"))]
+    FrameDecode {
+        backtrace: Backtrace,
+        framing: Framing,
+    },
+    #[snafu(display("Failed to read system id"))]
+    SystemIdRead {
+        backtrace: Backtrace,
+        source: std::io::Error,
+        data: Vec<u8>,
+    },
+    #[snafu(display("Failed to read message id"))]
+    MessageIdRead {
+        backtrace: Backtrace,
+        source: std::io::Error,
+        data: Vec<u8>,
+    },
+    /// The system id is read both ways since the endianness of the sender isn't known
+    #[snafu(display(
+        "System {big_endian_id:x} not found! ({little_endian_id:x} when read as little endian)"
+    ))]
+    UnknownSystem {
+        backtrace: Backtrace,
+        big_endian_id: u16,
+        little_endian_id: u16,
+        data: Vec<u8>,
+    },
+    #[snafu(display("{crc} mismatch (expected {expected:#x}, computed {computed:#x})"))]
+    CrcMismatch {
+        backtrace: Backtrace,
+        crc: FrameCrc,
+        expected: u32,
+        computed: u32,
+        data: Vec<u8>,
+    },
+    #[snafu(display("Frame is too short to hold its {crc}"))]
+    CrcMissing {
+        backtrace: Backtrace,
+        crc: FrameCrc,
+        data: Vec<u8>,
+    },
+    #[snafu(display("Failed to read frame header extension"))]
+    HeaderRead {
+        backtrace: Backtrace,
+        source: std::io::Error,
+        data: Vec<u8>,
+    },
+    #[snafu(display("Message {message_id:x} not found in system {system_id:x}!"))]
+    UnknownMessage {
+        backtrace: Backtrace,
+        system_id: u16,
+        message_id: u16,
+        data: Vec<u8>,
+        /// Entries lost right before this frame, see [`DecodeError::lost`]
+        lost: u32,
+    },
+    #[snafu(display("Failed to format message"))]
+    Format {
+        #[snafu(backtrace)]
+        source: ULogMessageFormatError,
+        message: Box<ULogMessage>,
+        data: Vec<u8>,
+        /// Entries lost right before this frame, see [`DecodeError::lost`]
+        lost: u32,
+    },
+}
+
+impl DecodeError {
+    /// The decoded frame, if decoding got that far
+    pub fn data(&self) -> Option<&[u8]> {
+        match self {
+            DecodeError::EntryRead { .. } | DecodeError::FrameDecode { .. } => None,
+            DecodeError::SystemIdRead { data, .. }
+            | DecodeError::MessageIdRead { data, .. }
+            | DecodeError::UnknownSystem { data, .. }
+            | DecodeError::CrcMismatch { data, .. }
+            | DecodeError::CrcMissing { data, .. }
+            | DecodeError::HeaderRead { data, .. }
+            | DecodeError::UnknownMessage { data, .. }
+            | DecodeError::Format { data, .. } => Some(data),
+        }
+    }
+
+    /// Whether the frame was corrupted in transit, as opposed to not matching the maps
+    pub fn is_corruption(&self) -> bool {
+        matches!(
+            self,
+            DecodeError::CrcMismatch { .. } | DecodeError::CrcMissing { .. }
+        )
+    }
+
+    /// Number of entries of the system lost right before this frame, according to the sequence
+    /// counters. Only known once the frame header extension was read
+    pub fn lost(&self) -> u32 {
+        match self {
+            DecodeError::UnknownMessage { lost, .. } | DecodeError::Format { lost, .. } => *lost,
+            _ => 0,
+        }
+    }
+
+    /// The message definition of the frame, if decoding got that far
+    pub fn message(&self) -> Option<&ULogMessage> {
+        match self {
+            DecodeError::Format { message, .. } => Some(message),
+            _ => None,
+        }
+    }
+}
+
+/// Counters of everything decoded so far
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DecodeStatistics {
+    /// Frames decoded into entries
+    pub decoded: u64,
+    /// Frames that failed their integrity check
+    pub corrupted: u64,
+    /// Frames that failed to decode for any other reason
+    pub failed: u64,
+    /// Entries that never arrived intact, according to the sequence counters
+    pub lost: u64,
+}
+
+impl DecodeStatistics {
+    fn record<T>(&mut self, result: &Result<T, DecodeError>) {
+        match result {
+            Ok(_) => self.decoded += 1,
+            Err(err) if err.is_corruption() => self.corrupted += 1,
+            Err(_) => self.failed += 1,
+        }
+    }
+}
+
+/// Identifies the frames of one system in one stream, by the source tag of the stream. Several
+/// boards running the same firmware each get their own device clock and sequence counter
+type StreamKey = (Option<Arc<str>>, u16);
+
+/// Decodes uLog frames using the maps of all the loaded systems
+#[derive(Debug, Clone, Default)]
+pub struct Decoder {
+    /// Loaded systems, by system id
+    systems: HashMap<u16, ULogSystemInfo>,
+    /// Reconstructed device clocks, by stream and system id
+    clocks: HashMap<StreamKey, DeviceClock>,
+    /// Sequence counter trackers, by stream and system id
+    sequences: HashMap<StreamKey, SequenceTracker>,
+    /// Encoding of the frames
+    framing: Framing,
+    /// Wire endianness of the systems that don't declare one
+    default_endianness: Endianness,
+    /// Refuse to load maps with lint issues other than warnings
+    strict: bool,
+    /// Counters of everything decoded so far
+    statistics: DecodeStatistics,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn systems(&self) -> &HashMap<u16, ULogSystemInfo> {
+        &self.systems
+    }
+
+    pub fn statistics(&self) -> DecodeStatistics {
+        self.statistics
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Sets how frames are encoded, and delimited in the streams given to [`Decoder::entries`]
+    pub fn set_framing(&mut self, framing: Framing) {
+        self.framing = framing;
+    }
+
+    pub fn default_endianness(&self) -> Endianness {
+        self.default_endianness
+    }
+
+    /// Sets the wire endianness of the systems that don't declare one in their meta section
+    pub fn set_default_endianness(&mut self, endianness: Endianness) {
+        self.default_endianness = endianness;
+    }
+
+    pub fn strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Sets whether [`Decoder::load_map`] refuses maps with lint issues other than warnings, see
+    /// [`lint_system`]
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Adds a system to the decoder, fails if its system id is already in use
+    pub fn add_system(&mut self, system: ULogSystemInfo) -> Result<(), SystemLoadError> {
+        let system_id = system.system_id();
+        if self.systems.contains_key(&system_id) {
+            return DuplicateSystemIdSnafu { system_id }.fail();
+        }
+        self.systems.insert(system_id, system);
+        Ok(())
+    }
+
+    /// Loads the uLog map of an ELF file or dictionary and adds its system to the decoder
+    pub fn load_map(&mut self, path: &Path) -> Result<&ULogSystemInfo, SystemLoadError> {
+        let system = load_system(path)?;
+        if self.strict {
+            let issues = lint_system(&system)
+                .into_iter()
+                .filter(|x| !x.is_warning())
+                .collect::<Vec<_>>();
+            ensure!(issues.is_empty(), LintSnafu { issues });
+        }
+        let system_id = system.system_id();
+        self.add_system(system)?;
+        Ok(&self.systems[&system_id])
+    }
+
+    /// Forgets the device clocks and sequence counters of the stream whose frames are tagged with
+    /// `source`, None standing for untagged frames. To be called when a connection is replaced,
+    /// the new one starts a new stream
+    pub fn reset_stream(&mut self, source: Option<&str>) {
+        self.clocks.retain(|(x, _), _| x.as_deref() != source);
+        self.sequences.retain(|(x, _), _| x.as_deref() != source);
+    }
+
+    /// Decodes a frame read from a stream, the entry keeps the frame's receive time and source tag
+    pub fn decode_raw(&mut self, frame: &RawFrame) -> Result<DecodedEntry, DecodeError> {
+        let mut entry = self.decode_stream_frame(&frame.data, frame.source.as_ref())?;
+        entry.received = Some(frame.timestamp);
+        Ok(entry)
+    }
+
+    /// Decodes a single encoded frame, without its delimiter
+    pub fn decode_frame(&mut self, frame: &[u8]) -> Result<DecodedEntry, DecodeError> {
+        self.decode_stream_frame(frame, None)
+    }
+
+    /// Decodes a single encoded frame of the stream tagged with `source`
+    fn decode_stream_frame(
+        &mut self,
+        frame: &[u8],
+        source: Option<&Arc<str>>,
+    ) -> Result<DecodedEntry, DecodeError> {
+        let data = self.framing.decode(frame).context(FrameDecodeSnafu {
+            framing: self.framing,
+        });
+        match data {
+            Ok(data) => self.decode_stream_payload(&data, source),
+            Err(err) => {
+                self.statistics.failed += 1;
+                Err(err)
+            }
+        }
+    }
+
+    /// Checks whether an encoded frame refers to a known message of a loaded system,
+    /// without reading its arguments
+    pub fn recognizes(&self, frame: &[u8]) -> bool {
+        let Some(data) = self.framing.decode(frame) else {
+            return false;
+        };
+        let Some((system, endianness)) = data
+            .first_chunk()
+            .and_then(|ids| find_system(&self.systems, self.default_endianness, *ids))
+        else {
+            return false;
+        };
+        endianness
+            .read_u16(&mut &data[2..])
+            .is_ok_and(|x| system.messages().contains_key(&x))
+    }
+
+    /// Creates a recognizer of the frames of the systems loaded so far, that can be handed to the
+    /// sources reading streams on other threads
+    pub fn recognizer(&self) -> FrameRecognizer {
+        let systems = self
+            .systems
+            .iter()
+            .map(|(id, system)| {
+                let endianness = system.meta().endianness.unwrap_or(self.default_endianness);
+                let messages = system.messages().keys().copied().collect();
+                (*id, (endianness, messages))
+            })
+            .collect();
+        FrameRecognizer {
+            framing: self.framing,
+            systems: Arc::new(systems),
+        }
+    }
+
+    /// Decodes the payload of a frame once its encoding has been removed
+    pub fn decode_payload(&mut self, data: &[u8]) -> Result<DecodedEntry, DecodeError> {
+        self.decode_stream_payload(data, None)
+    }
+
+    /// Decodes the payload of a frame of the stream tagged with `source`
+    fn decode_stream_payload(
+        &mut self,
+        data: &[u8],
+        source: Option<&Arc<str>>,
+    ) -> Result<DecodedEntry, DecodeError> {
+        let result = self.read_payload(data, source);
+        self.statistics.record(&result);
+        result
+    }
+
+    fn read_payload(
+        &mut self,
+        data: &[u8],
+        source: Option<&Arc<str>>,
+    ) -> Result<DecodedEntry, DecodeError> {
+        // Funky ref taking because the read_* functions want a mut ref to a ref which is awkward
+        let reader = &mut &data[..];
+
+        // Find the system from the system map, which also tells the endianness of the rest
+        let mut ids = [0; 2];
+        reader
+            .read_exact(&mut ids)
+            .with_context(|_| SystemIdReadSnafu { data })?;
+        let (system, endianness) = find_system(&self.systems, self.default_endianness, ids)
+            .with_context(|| UnknownSystemSnafu {
+                big_endian_id: u16::from_be_bytes(ids),
+                little_endian_id: u16::from_le_bytes(ids),
+                data,
+            })?;
+        let system_id = system.system_id();
+        let stream = (source.cloned(), system_id);
+        let message_id = endianness
+            .read_u16(reader)
+            .with_context(|_| MessageIdReadSnafu { data })?;
+
+        // Check the CRC trailer and leave it out of what is left to read
+        if let Some(crc) = system.meta().crc {
+            // The ids are covered too, they have to fit before the CRC
+            let (covered, expected) = crc
+                .split(data, endianness)
+                .filter(|(covered, _)| covered.len() >= 4)
+                .context(CrcMissingSnafu { crc, data })?;
+            let computed = crc.compute(covered);
+            ensure!(
+                computed == expected,
+                CrcMismatchSnafu {
+                    crc,
+                    expected,
+                    computed,
+                    data
+                }
+            );
+            *reader = &covered[4..];
+        }
+
+        // Read the optional header extension, it comes right after the ids
+        let header = FrameHeaderExtension::read(reader, system.meta().header_version, endianness)
+            .with_context(|_| HeaderReadSnafu { data })?;
+
+        // The entry arrived, even if it fails to decode past this point
+        let lost = header
+            .sequence
+            .map(|x| self.sequences.entry(stream.clone()).or_default().update(x))
+            .unwrap_or_default();
+        self.statistics.lost += lost as u64;
+
+        // Get the message template from the system's message map
+        let message = system
+            .messages()
+            .get(&message_id)
+            .with_context(|| UnknownMessageSnafu {
+                system_id,
+                message_id,
+                data,
+                lost,
+            })?;
+
+        // Let the message read in its arguments, then format them into the message
+        let arguments = message
+            .read_values(reader, system.ulog_strings(), system.memory(), endianness)
+            .and_then(|args| message.format_values(&args).map(|text| (args, text)));
+        let (arguments, text) = arguments.with_context(|_| FormatSnafu {
+            message: Box::new(message.clone()),
+            data,
+            lost,
+        })?;
+
+        // Turn the device ticks into a monotonic device time
+        let device_time = header.ticks.map(|ticks| {
+            self.clocks
+                .entry(stream)
+                .or_default()
+                .update(ticks, system.meta().tick_rate)
+        });
+
+        Ok(DecodedEntry {
+            system_id,
+            message_id,
+            severity_level: message.severity_level(),
+            location: message.location().clone(),
+            format: message.format().to_string(),
+            text,
+            arguments: arguments.iter().filter_map(ULogArgument::value).collect(),
+            received: None,
+            device_time,
+            lost,
+            source: source.cloned(),
+        })
+    }
+
+    /// Iterator adapter that decodes every frame of a stream
+    pub fn entries<R: BufRead>(&mut self, reader: R) -> DecodedEntries<'_, R> {
+        let framing = self.framing;
+        DecodedEntries {
+            decoder: self,
+            frames: FrameReader::new(reader).with_framing(framing),
+            last_timestamp: None,
+        }
+    }
+}
+
+/// Loads the uLog map of an ELF file or dictionary, telling them apart by the ELF magic number
+pub fn load_system(path: &Path) -> Result<ULogSystemInfo, SystemLoadError> {
+    let mut magic = [0; 4];
+    // Files that can't be read are left to the ELF loader to report
+    let is_elf = File::open(path)
+        .and_then(|mut x| x.read_exact(&mut magic))
+        .map(|_| magic == *b"\x7fELF")
+        .unwrap_or(true);
+    if is_elf {
+        attempt_load_elf(path).context(ElfSnafu)
+    } else {
+        load_dictionary(path).context(DictionarySnafu)
+    }
+}
+
+/// Finds the system a frame comes from using the first two bytes of the frame. Ids are in the wire
+/// endianness of their system, so the system id is looked up as big endian then as little endian
+/// Tells the frames of a set of systems apart from other data, see [`Decoder::recognizer`]
+#[derive(Debug, Clone, Default)]
+pub struct FrameRecognizer {
+    /// Encoding of the frames
+    framing: Framing,
+    /// Wire endianness and message ids of the systems, by system id
+    systems: Arc<HashMap<u16, (Endianness, HashSet<u16>)>>,
+}
+
+impl FrameRecognizer {
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Checks whether an encoded frame refers to a known message of one of the systems, the same
+    /// way as [`Decoder::recognizes`]
+    pub fn recognizes(&self, frame: &[u8]) -> bool {
+        let Some(data) = self.framing.decode(frame) else {
+            return false;
+        };
+        let Some(ids) = data.first_chunk() else {
+            return false;
+        };
+        let system = [Endianness::Big, Endianness::Little]
+            .into_iter()
+            .find_map(|endianness| {
+                let system_id = match endianness {
+                    Endianness::Big => u16::from_be_bytes(*ids),
+                    Endianness::Little => u16::from_le_bytes(*ids),
+                };
+                self.systems
+                    .get(&system_id)
+                    .filter(|(x, _)| *x == endianness)
+            });
+        system.is_some_and(|(endianness, messages)| {
+            endianness
+                .read_u16(&mut &data[2..])
+                .is_ok_and(|x| messages.contains(&x))
+        })
+    }
+}
+
+fn find_system(
+    systems: &HashMap<u16, ULogSystemInfo>,
+    default_endianness: Endianness,
+    ids: [u8; 2],
+) -> Option<(&ULogSystemInfo, Endianness)> {
+    [Endianness::Big, Endianness::Little]
+        .into_iter()
+        .find_map(|endianness| {
+            let system_id = match endianness {
+                Endianness::Big => u16::from_be_bytes(ids),
+                Endianness::Little => u16::from_le_bytes(ids),
+            };
+            systems
+                .get(&system_id)
+                .filter(|x| x.meta().endianness.unwrap_or(default_endianness) == endianness)
+                .map(|x| (x, endianness))
+        })
+}
+
+/// Iterator over the decoded entries of a stream, see [`Decoder::entries`]
+pub struct DecodedEntries<'a, R: BufRead> {
+    decoder: &'a mut Decoder,
+    frames: FrameReader<R>,
+    /// Time at which the last frame was received
+    last_timestamp: Option<ReceiveTimestamp>,
+}
+
+impl<R: BufRead> DecodedEntries<'_, R> {
+    /// Bytes of the last frame read, as they were received
+    pub fn last_frame(&self) -> &[u8] {
+        self.frames.last_frame()
+    }
+
+    /// Time at which the last frame was received, None if it could not be read
+    pub fn last_timestamp(&self) -> Option<ReceiveTimestamp> {
+        self.last_timestamp
+    }
+}
+
+impl<R: BufRead> Iterator for DecodedEntries<'_, R> {
+    type Item = Result<DecodedEntry, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.frames.next()?;
+        self.last_timestamp = frame.as_ref().ok().map(|frame| frame.timestamp);
+        Some(
+            frame
+                .context(EntryReadSnafu)
+                .and_then(|frame| self.decoder.decode_raw(&frame)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decoder::{DecodeError, Decoder};
+    use crate::endianness::Endianness;
+    use crate::frame_crc::FrameCrc;
+    use crate::frame_reader::RawFrame;
+    use crate::framing::Framing;
+    use crate::test_support::SystemBuilder;
+    use crate::timestamp::ReceiveTimestamp;
+    use crate::ulog_system_info::{ULogSystemInfo, ULogSystemMeta};
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    /// System with a single message 5 printing a u32
+    fn system(system_id: u16, meta: ULogSystemMeta) -> ULogSystemInfo {
+        SystemBuilder::new(system_id)
+            .message(5, "value {}", &[251])
+            .meta(meta)
+            .build()
+    }
+
+    /// Payload of message 5 of system 0x0102, with a sequence counter and a CRC-16
+    fn payload(sequence: u16, value: u32) -> Vec<u8> {
+        let mut data = vec![0x01, 0x02, 0x00, 0x05, 0b10];
+        data.extend_from_slice(&sequence.to_be_bytes());
+        data.extend_from_slice(&value.to_be_bytes());
+        let crc = FrameCrc::Crc16.compute(&data) as u16;
+        data.extend_from_slice(&crc.to_be_bytes());
+        data
+    }
+
+    fn decoder() -> Decoder {
+        let mut decoder = Decoder::new();
+        decoder.set_framing(Framing::Unframed);
+        let meta = ULogSystemMeta {
+            header_version: 1,
+            crc: Some(FrameCrc::Crc16),
+            ..Default::default()
+        };
+        decoder.add_system(system(0x0102, meta)).unwrap();
+        let meta = ULogSystemMeta {
+            endianness: Some(Endianness::Little),
+            ..Default::default()
+        };
+        decoder.add_system(system(0x0304, meta)).unwrap();
+        decoder
+    }
+
+    #[test]
+    fn decode() {
+        let mut decoder = decoder();
+        let entry = decoder.decode_payload(&payload(1, 7)).unwrap();
+        assert_eq!((entry.system_id, entry.message_id), (0x0102, 5));
+        assert_eq!(entry.text, "value 7");
+
+        // The ids and the argument of the little endian system are read as such
+        let entry = decoder
+            .decode_payload(&[0x04, 0x03, 0x05, 0x00, 0x08, 0x00, 0x00, 0x00])
+            .unwrap();
+        assert_eq!((entry.system_id, entry.text.as_str()), (0x0304, "value 8"));
+
+        assert!(matches!(
+            decoder.decode_payload(&[0x09, 0x0A, 0x00, 0x05]),
+            Err(DecodeError::UnknownSystem {
+                big_endian_id: 0x090A,
+                little_endian_id: 0x0A09,
+                ..
+            })
+        ));
+        assert!(matches!(
+            decoder.decode_payload(&[0x04, 0x03, 0x06, 0x00]),
+            Err(DecodeError::UnknownMessage {
+                system_id: 0x0304,
+                message_id: 6,
+                ..
+            })
+        ));
+
+        let statistics = decoder.statistics();
+        assert_eq!((statistics.decoded, statistics.failed), (2, 2));
+    }
+
+    #[test]
+    fn crc() {
+        let mut decoder = decoder();
+        let mut data = payload(1, 7);
+        *data.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            decoder.decode_payload(&data),
+            Err(DecodeError::CrcMismatch { .. })
+        ));
+        assert!(matches!(
+            decoder.decode_payload(&data[..3]),
+            Err(DecodeError::MessageIdRead { .. })
+        ));
+        assert!(matches!(
+            decoder.decode_payload(&data[..5]),
+            Err(DecodeError::CrcMissing { .. })
+        ));
+        assert_eq!(decoder.statistics().corrupted, 2);
+    }
+
+    #[test]
+    fn lost() {
+        let mut decoder = decoder();
+        let frame = |sequence, source: &str| RawFrame {
+            data: payload(sequence, 7),
+            timestamp: ReceiveTimestamp::now(Instant::now()),
+            source: Some(Arc::from(source)),
+            text: false,
+        };
+        assert_eq!(decoder.decode_raw(&frame(1, "a")).unwrap().lost, 0);
+        assert_eq!(decoder.decode_raw(&frame(4, "a")).unwrap().lost, 2);
+        // Every stream has its own counter
+        assert_eq!(decoder.decode_raw(&frame(1, "b")).unwrap().lost, 0);
+        assert_eq!(decoder.decode_raw(&frame(5, "a")).unwrap().lost, 0);
+
+        // A new connection starts counting again
+        decoder.reset_stream(Some("a"));
+        assert_eq!(decoder.decode_raw(&frame(9, "a")).unwrap().lost, 0);
+
+        // A gap is still reported when the frame right after it fails to decode, here because its
+        // argument is missing
+        let mut truncated = frame(12, "a");
+        truncated.data = vec![0x01, 0x02, 0x00, 0x05, 0b10, 0x00, 0x0C];
+        let crc = FrameCrc::Crc16.compute(&truncated.data) as u16;
+        truncated.data.extend_from_slice(&crc.to_be_bytes());
+        let err = decoder.decode_raw(&truncated).unwrap_err();
+        assert!(matches!(err, DecodeError::Format { .. }));
+        assert_eq!(err.lost(), 2);
+        assert_eq!(decoder.statistics().lost, 4);
+    }
+}
@@ -0,0 +1,347 @@
+use crate::dwarf::{EnumType, EnumTypeMap};
+use crate::endianness::Endianness;
+use crate::frame_crc::FrameCrc;
+use crate::frame_header::MAX_HEADER_VERSION;
+use crate::location::Location;
+use crate::placeholder::Annotation;
+use crate::severity::SeverityLevel;
+use crate::symbolizer::Symbolizer;
+use crate::target_memory::TargetMemory;
+use crate::ulog_argument::{ULogArgument, ULogArgumentParseError};
+use crate::ulog_message::{ULogMessage, ULogMessageMap};
+use crate::ulog_string::{ULogString, ULogStringMap};
+use crate::ulog_system_info::{ULogSystemInfo, ULogSystemMeta};
+use serde::{Deserialize, Serialize};
+use snafu::{Backtrace, OptionExt, ResultExt, Snafu, ensure};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Value of the `format` field of every dictionary
+const DICTIONARY_FORMAT: &str = "ulog-map";
+
+/// Version of the dictionary format written by this decoder, the newest one it reads. Versions
+/// start at 1
+pub const DICTIONARY_VERSION: u32 = 1;
+
+#[derive(Snafu, Debug)]
+pub enum DictionaryError {
+    #[snafu(display("Failed to open file"))]
+    File {
+        backtrace: Backtrace,
+        source: std::io::Error,
+    },
+    #[snafu(display("The dictionary could not be parsed"))]
+    Parse {
+        backtrace: Backtrace,
+        source: serde_json::Error,
+    },
+    #[snafu(display("The dictionary could not be written"))]
+    Write {
+        backtrace: Backtrace,
+        source: serde_json::Error,
+    },
+    #[snafu(display("Not a uLog map dictionary"))]
+    NotADictionary { backtrace: Backtrace },
+    #[snafu(display("Dictionary version {version} is not supported"))]
+    UnsupportedVersion { backtrace: Backtrace, version: u32 },
+    #[snafu(display("Frame header version {version} is not supported"))]
+    UnsupportedHeaderVersion { backtrace: Backtrace, version: u8 },
+    #[snafu(display("{width} bit frame CRCs are not supported"))]
+    UnsupportedCrc { backtrace: Backtrace, width: u8 },
+    #[snafu(display("Message id {id} is declared more than once"))]
+    DuplicateMessageId { backtrace: Backtrace, id: u16 },
+    #[snafu(display("String id {id} is declared more than once"))]
+    DuplicateStringId { backtrace: Backtrace, id: u16 },
+    #[snafu(display("Invalid template string for message {id}: {template}"))]
+    TemplateParse {
+        backtrace: Backtrace,
+        source: dyf::Error,
+        id: u16,
+        template: String,
+    },
+    #[snafu(display("Invalid argument for message {id}"))]
+    ArgumentParse {
+        #[snafu(backtrace)]
+        source: ULogArgumentParseError,
+        id: u16,
+    },
+}
+
+/// Standalone uLog map of a system, holding everything needed to decode its entries without the
+/// ELF file. Only the enum types the messages refer to are kept, addresses can't be described and
+/// constant strings sent by address can't be read without the ELF file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dictionary {
+    /// Always `ulog-map`
+    pub format: String,
+    pub version: u32,
+    pub system_id: u16,
+    pub meta: DictionaryMeta,
+    pub strings: Vec<DictionaryString>,
+    pub messages: Vec<DictionaryMessage>,
+    /// Enumerators of each enum type by value
+    pub enums: BTreeMap<String, BTreeMap<i64, String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DictionaryMeta {
+    pub header_version: u8,
+    pub tick_rate: Option<u32>,
+    /// Width of the CRC in bits
+    pub crc: Option<u8>,
+    pub endianness: Option<Endianness>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DictionaryString {
+    pub id: u16,
+    pub string: String,
+    pub file: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DictionaryMessage {
+    pub id: u16,
+    pub severity: SeverityLevel,
+    pub file: String,
+    pub line: usize,
+    /// Format string as written in the source
+    pub format: String,
+    /// Type id of each argument
+    pub arguments: Vec<u8>,
+}
+
+impl Dictionary {
+    pub fn from_system(system: &ULogSystemInfo) -> Self {
+        let meta = system.meta();
+
+        let mut strings = system
+            .ulog_strings()
+            .values()
+            .map(|x| DictionaryString {
+                id: x.id(),
+                string: x.string().to_string(),
+                file: x.location().file.to_string(),
+                line: x.location().line,
+            })
+            .collect::<Vec<_>>();
+        strings.sort_by_key(|x| x.id);
+
+        let mut messages = system
+            .messages()
+            .values()
+            .map(|x| DictionaryMessage {
+                id: x.id(),
+                severity: x.severity_level(),
+                file: x.location().file.to_string(),
+                line: x.location().line,
+                format: x.format().to_string(),
+                arguments: x.arguments().iter().map(|x| x.type_id()).collect(),
+            })
+            .collect::<Vec<_>>();
+        messages.sort_by_key(|x| x.id);
+
+        let enums = system
+            .messages()
+            .values()
+            .flat_map(|x| x.annotations().iter().flatten())
+            .filter_map(|x| match x {
+                Annotation::Enum { ty: Some(ty), .. } => Some(ty),
+                _ => None,
+            })
+            .map(|x| {
+                let enumerators = x.enumerators.clone().into_iter().collect();
+                (x.name.clone(), enumerators)
+            })
+            .collect();
+
+        Self {
+            format: DICTIONARY_FORMAT.to_string(),
+            version: DICTIONARY_VERSION,
+            system_id: system.system_id(),
+            meta: DictionaryMeta {
+                header_version: meta.header_version,
+                tick_rate: meta.tick_rate,
+                crc: meta.crc.map(|x| x.width()),
+                endianness: meta.endianness,
+            },
+            strings,
+            messages,
+            enums,
+        }
+    }
+
+    pub fn into_system(self) -> Result<ULogSystemInfo, DictionaryError> {
+        ensure!(self.format == DICTIONARY_FORMAT, NotADictionarySnafu);
+        ensure!(
+            (1..=DICTIONARY_VERSION).contains(&self.version),
+            UnsupportedVersionSnafu {
+                version: self.version
+            }
+        );
+
+        let mut strings = ULogStringMap::new();
+        for x in self.strings {
+            ensure!(
+                !strings.contains_key(&x.id),
+                DuplicateStringIdSnafu { id: x.id }
+            );
+            let location = Location {
+                file: Arc::new(x.file),
+                line: x.line,
+            };
+            strings.insert(x.id, ULogString::new(x.id, x.string, location));
+        }
+
+        let enum_types = self
+            .enums
+            .into_iter()
+            .map(|(name, enumerators)| {
+                let ty = EnumType {
+                    name: name.clone(),
+                    enumerators: enumerators.into_iter().collect(),
+                };
+                (name, Arc::new(ty))
+            })
+            .collect::<EnumTypeMap>();
+        // There is nothing to describe addresses with
+        let symbolizer = Arc::new(Symbolizer::default());
+
+        let mut messages = ULogMessageMap::new();
+        for x in self.messages {
+            ensure!(
+                !messages.contains_key(&x.id),
+                DuplicateMessageIdSnafu { id: x.id }
+            );
+            let location = Location {
+                file: Arc::new(x.file),
+                line: x.line,
+            };
+            let mut message = ULogMessage::new(x.id, x.format.clone(), location, x.severity)
+                .context(TemplateParseSnafu {
+                    id: x.id,
+                    template: x.format,
+                })?;
+            for type_id in x.arguments {
+                let argument =
+                    ULogArgument::try_from(type_id).context(ArgumentParseSnafu { id: x.id })?;
+                message.arguments_mut().push(argument);
+            }
+            message.resolve_annotations(&enum_types, &symbolizer);
+            messages.insert(x.id, message);
+        }
+
+        ensure!(
+            self.meta.header_version <= MAX_HEADER_VERSION,
+            UnsupportedHeaderVersionSnafu {
+                version: self.meta.header_version
+            }
+        );
+        // A width of 0 stands for no CRC, as in the meta section of ELF files
+        let crc = self
+            .meta
+            .crc
+            .filter(|x| *x != 0)
+            .map(|width| FrameCrc::from_width(width).context(UnsupportedCrcSnafu { width }))
+            .transpose()?;
+        Ok(ULogSystemInfo::new(
+            strings,
+            messages,
+            self.system_id,
+            ULogSystemMeta {
+                header_version: self.meta.header_version,
+                tick_rate: self.meta.tick_rate,
+                crc,
+                endianness: self.meta.endianness,
+            },
+            TargetMemory::default(),
+        ))
+    }
+}
+
+/// Loads a system from a dictionary file
+pub fn load_dictionary(path: &Path) -> Result<ULogSystemInfo, DictionaryError> {
+    let file = File::open(path).context(FileSnafu)?;
+    let dictionary: Dictionary =
+        serde_json::from_reader(BufReader::new(file)).context(ParseSnafu)?;
+    dictionary.into_system()
+}
+
+/// Writes the dictionary of a system as JSON
+pub fn write_dictionary(
+    system: &ULogSystemInfo,
+    mut writer: impl Write,
+) -> Result<(), DictionaryError> {
+    serde_json::to_writer_pretty(&mut writer, &Dictionary::from_system(system))
+        .and_then(|_| writeln!(writer).map_err(serde_json::Error::io))
+        .context(WriteSnafu)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dictionary::{Dictionary, DictionaryError, write_dictionary};
+    use crate::severity::SeverityLevel;
+    use crate::test_support::{SystemBuilder, location};
+    use std::error::Error;
+
+    #[test]
+    fn round_trip() -> Result<(), Box<dyn Error>> {
+        let system = SystemBuilder::new(0x0102)
+            .message_at(
+                3,
+                "value {:x}",
+                &[242],
+                location("main.c", 20),
+                SeverityLevel::Warning,
+            )
+            .build();
+
+        let mut json = vec![];
+        write_dictionary(&system, &mut json)?;
+        let system = serde_json::from_slice::<Dictionary>(&json)?.into_system()?;
+        assert_eq!(system.system_id(), 0x0102);
+        let message = &system.messages()[&3];
+        assert_eq!(message.format(), "value {:x}");
+        assert_eq!(message.severity_level().to_string(), "Warning");
+        assert_eq!(message.arguments()[0].type_id(), 242);
+        Ok(())
+    }
+
+    #[test]
+    fn validation() {
+        let system = SystemBuilder::new(0x0102)
+            .message(3, "boot", &[])
+            .string(0, "idle", location("main.c", 1))
+            .build();
+        let dictionary = Dictionary::from_system(&system);
+
+        let mut zero_crc = dictionary.clone();
+        zero_crc.meta.crc = Some(0);
+        assert!(zero_crc.into_system().unwrap().meta().crc.is_none());
+
+        let mut version = dictionary.clone();
+        version.version = 0;
+        assert!(matches!(
+            version.into_system(),
+            Err(DictionaryError::UnsupportedVersion { version: 0, .. })
+        ));
+
+        let mut messages = dictionary.clone();
+        messages.messages.push(messages.messages[0].clone());
+        assert!(matches!(
+            messages.into_system(),
+            Err(DictionaryError::DuplicateMessageId { id: 3, .. })
+        ));
+
+        let mut strings = dictionary;
+        strings.strings.push(strings.strings[0].clone());
+        assert!(matches!(
+            strings.into_system(),
+            Err(DictionaryError::DuplicateStringId { id: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,277 @@
+use crate::location::Location;
+use crate::section::{SectionReadError, read_section};
+use elf::ElfStream;
+use elf::endian::AnyEndian;
+use gimli::{AttributeValue, DwarfSections, EndianSlice, RunTimeEndian, UnitOffset};
+use snafu::{Backtrace, ResultExt, Snafu};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Read, Seek};
+use std::sync::Arc;
+
+#[derive(Snafu, Debug)]
+pub enum DwarfError {
+    #[snafu(display("The ELF file could not be parsed"))]
+    ElfParse {
+        backtrace: Backtrace,
+        source: elf::ParseError,
+    },
+    #[snafu(display("Failed to read section {name}"))]
+    SectionRead {
+        #[snafu(backtrace)]
+        source: SectionReadError,
+        name: String,
+    },
+    #[snafu(display("The debug info could not be parsed"))]
+    DwarfParse {
+        backtrace: Backtrace,
+        source: gimli::Error,
+    },
+}
+
+/// Enum type declared in the debug info
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnumType {
+    /// Name of the type, either its own or the one of a typedef naming it
+    pub name: String,
+    /// Name of each enumerator by value
+    pub enumerators: HashMap<i64, String>,
+}
+
+impl EnumType {
+    /// Gets the name of the enumerator with a given value
+    pub fn enumerator(&self, value: i64) -> Option<&str> {
+        self.enumerators.get(&value).map(|x| x.as_str())
+    }
+}
+
+/// Helper type for a map of type names to enum types
+pub type EnumTypeMap = HashMap<String, Arc<EnumType>>;
+
+/// Enum types of the debug info by name, see [`DebugInfo::enum_types`]
+#[derive(Debug, Clone, Default)]
+pub struct EnumTypes {
+    /// Types whose name always comes with the same enumerators
+    pub types: EnumTypeMap,
+    /// Names of the types declared with different enumerators by several compilation units,
+    /// which of them a format string refers to can't be known
+    pub conflicts: BTreeSet<String>,
+}
+
+impl EnumTypes {
+    /// Adds a type, the same one is usually declared by every compilation unit including its
+    /// header
+    fn insert(&mut self, ty: EnumType) {
+        if self.conflicts.contains(&ty.name) {
+            return;
+        }
+        match self.types.get(&ty.name) {
+            Some(x) if x.enumerators != ty.enumerators => {
+                self.types.remove(&ty.name);
+                self.conflicts.insert(ty.name);
+            }
+            Some(_) => {}
+            None => {
+                self.types.insert(ty.name.clone(), Arc::new(ty));
+            }
+        }
+    }
+}
+
+/// Row of a line table, the code starting at its address comes from its location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineRow {
+    pub address: u64,
+    /// None at the end of a sequence, the code after it isn't described by this sequence
+    pub location: Option<Location>,
+}
+
+/// DWARF debug info of an ELF file, empty when the file has none
+pub struct DebugInfo {
+    sections: DwarfSections<Vec<u8>>,
+    endian: RunTimeEndian,
+}
+
+impl DebugInfo {
+    /// Reads the debug sections of an ELF file
+    pub fn load<R: Read + Seek>(
+        elf_file: &mut ElfStream<AnyEndian, R>,
+    ) -> Result<Self, DwarfError> {
+        let endian = match elf_file.ehdr.endianness {
+            AnyEndian::Little => RunTimeEndian::Little,
+            AnyEndian::Big => RunTimeEndian::Big,
+        };
+        let sections = DwarfSections::load(|id| {
+            let name = id.name();
+            let Some(header) = elf_file
+                .section_header_by_name(name)
+                .context(ElfParseSnafu)?
+                .copied()
+            else {
+                // Missing sections are read as empty ones
+                return Ok(vec![]);
+            };
+            read_section(elf_file, &header).context(SectionReadSnafu { name })
+        })?;
+        Ok(Self { sections, endian })
+    }
+
+    /// Collects every named enum type, anonymous enums are named after the typedefs naming them
+    pub fn enum_types(&self) -> Result<EnumTypes, DwarfError> {
+        let dwarf = self
+            .sections
+            .borrow(|x| EndianSlice::new(x.as_slice(), self.endian));
+        let mut types = EnumTypes::default();
+
+        let mut units = dwarf.units();
+        while let Some(header) = units.next().context(DwarfParseSnafu)? {
+            let unit = dwarf.unit(header).context(DwarfParseSnafu)?;
+            // Typedefs can come before the type they name, so they are only resolved once the
+            // whole unit has been read
+            let mut enums = HashMap::<UnitOffset, EnumType>::new();
+            let mut typedefs = vec![];
+            let mut current = None;
+
+            let mut entries = unit.entries();
+            while let Some((_, entry)) = entries.next_dfs().context(DwarfParseSnafu)? {
+                let name = entry
+                    .attr_value(gimli::DW_AT_name)
+                    .context(DwarfParseSnafu)?
+                    .map(|x| dwarf.attr_string(&unit, x))
+                    .transpose()
+                    .context(DwarfParseSnafu)?
+                    .map(|x| x.to_string_lossy().into_owned());
+
+                match entry.tag() {
+                    gimli::DW_TAG_enumeration_type => {
+                        current = Some(entry.offset());
+                        enums.insert(
+                            entry.offset(),
+                            EnumType {
+                                name: name.unwrap_or_default(),
+                                enumerators: HashMap::new(),
+                            },
+                        );
+                    }
+                    // Enumerators only ever appear as children of the enum they belong to
+                    gimli::DW_TAG_enumerator => {
+                        let value = match entry
+                            .attr_value(gimli::DW_AT_const_value)
+                            .context(DwarfParseSnafu)?
+                        {
+                            Some(AttributeValue::Sdata(x)) => x,
+                            Some(AttributeValue::Udata(x)) => x as i64,
+                            // Compilers only use the sized forms for positive values
+                            Some(x) => match x.udata_value() {
+                                Some(x) => x as i64,
+                                None => continue,
+                            },
+                            None => continue,
+                        };
+                        if let (Some(name), Some(ty)) =
+                            (name, current.and_then(|x| enums.get_mut(&x)))
+                        {
+                            ty.enumerators.insert(value, name);
+                        }
+                    }
+                    gimli::DW_TAG_typedef => {
+                        if let (Some(name), Some(AttributeValue::UnitRef(target))) = (
+                            name,
+                            entry
+                                .attr_value(gimli::DW_AT_type)
+                                .context(DwarfParseSnafu)?,
+                        ) {
+                            typedefs.push((name, target));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            for (name, target) in typedefs {
+                if let Some(ty) = enums.get(&target) {
+                    types.insert(EnumType {
+                        name,
+                        enumerators: ty.enumerators.clone(),
+                    });
+                }
+            }
+            for ty in enums.into_values().filter(|x| !x.name.is_empty()) {
+                types.insert(ty);
+            }
+        }
+        Ok(types)
+    }
+
+    /// Collects the rows of every line table, sorted by address
+    pub fn line_rows(&self) -> Result<Vec<LineRow>, DwarfError> {
+        let dwarf = self
+            .sections
+            .borrow(|x| EndianSlice::new(x.as_slice(), self.endian));
+        let mut line_rows = vec![];
+
+        let mut units = dwarf.units();
+        while let Some(header) = units.next().context(DwarfParseSnafu)? {
+            let unit = dwarf.unit(header).context(DwarfParseSnafu)?;
+            let Some(program) = unit.line_program.clone() else {
+                continue;
+            };
+
+            let mut rows = program.rows();
+            while let Some((header, row)) = rows.next_row().context(DwarfParseSnafu)? {
+                if row.end_sequence() {
+                    line_rows.push(LineRow {
+                        address: row.address(),
+                        location: None,
+                    });
+                    continue;
+                }
+                let (Some(file), Some(line)) = (row.file(header), row.line()) else {
+                    continue;
+                };
+                let file = dwarf
+                    .attr_string(&unit, file.path_name())
+                    .context(DwarfParseSnafu)?;
+                line_rows.push(LineRow {
+                    address: row.address(),
+                    location: Some(Location {
+                        file: Arc::new(file.to_string_lossy().into_owned()),
+                        line: line.get() as usize,
+                    }),
+                });
+            }
+        }
+
+        // A sequence can start where another one ends, the end must come first
+        line_rows.sort_by_key(|x| (x.address, x.location.is_some()));
+        Ok(line_rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dwarf::{EnumType, EnumTypes};
+    use std::collections::HashMap;
+
+    #[test]
+    fn conflicts() {
+        let ty = |name: &str, enumerators: &[(i64, &str)]| EnumType {
+            name: name.to_string(),
+            enumerators: enumerators
+                .iter()
+                .map(|(value, name)| (*value, name.to_string()))
+                .collect::<HashMap<_, _>>(),
+        };
+        let mut types = EnumTypes::default();
+        // Declared by a header included by two units
+        types.insert(ty("state_t", &[(0, "IDLE")]));
+        types.insert(ty("state_t", &[(0, "IDLE")]));
+        // Two unrelated types sharing a name
+        types.insert(ty("error_t", &[(0, "NONE")]));
+        types.insert(ty("error_t", &[(0, "OK")]));
+        types.insert(ty("error_t", &[(0, "NONE")]));
+
+        assert!(types.types.contains_key("state_t"));
+        assert!(!types.types.contains_key("error_t"));
+        assert!(types.conflicts.contains("error_t"));
+    }
+}
@@ -1,19 +1,28 @@
 #![allow(unused_variables)]
 
+use crate::dwarf::{DebugInfo, DwarfError, EnumTypes, LineRow};
+use crate::endianness::Endianness;
+use crate::frame_crc::FrameCrc;
+use crate::frame_header::MAX_HEADER_VERSION;
 use crate::location::Location;
+use crate::placeholder::Annotation;
+use crate::section::{SectionReadError, read_section};
 use crate::severity::{SeverityLevel, SeverityLevelParseError};
 use crate::splitter::{SplitSegmentError, split_segments};
+use crate::symbolizer::{SymbolRange, Symbolizer};
+use crate::target_memory::TargetMemory;
 use crate::ulog_argument::{ULogArgument, ULogArgumentParseError};
 use crate::ulog_message::{ULogMessage, ULogMessageMap};
 use crate::ulog_string::{ULogString, ULogStringMap};
-use crate::ulog_system_info::ULogSystemInfo;
-use dyf::FormatString;
+use crate::ulog_system_info::{ULogSystemInfo, ULogSystemMeta};
 use elf::ElfStream;
 use elf::endian::{AnyEndian, EndianParse};
+use elf::section::SectionHeader;
 use elf::symbol::Symbol;
 use itertools::Itertools;
-use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+use snafu::{Backtrace, ErrorCompat, OptionExt, ResultExt, Snafu};
 use std::fs;
+use std::io::{Read, Seek};
 use std::path::Path;
 use std::sync::Arc;
 
@@ -42,8 +51,12 @@ pub enum ElfParseError {
     NoStringTable { backtrace: Backtrace },
     #[snafu(display(".ulog section not found"))]
     NoULogSection { backtrace: Backtrace },
-    #[snafu(display("ulog section is compressed. Compressions sections are not supported."))]
-    ULogSectionCompressed { backtrace: Backtrace },
+    #[snafu(display("Failed to read section {name}"))]
+    SectionRead {
+        #[snafu(backtrace)]
+        source: SectionReadError,
+        name: String,
+    },
     #[snafu(display("The ELF file could not be parsed"))]
     ElfParse {
         source: elf::ParseError,
@@ -64,6 +77,12 @@ pub enum ElfParseError {
     },
     #[snafu(display("Cannot find system id"))]
     NoSystemId { backtrace: Backtrace },
+    #[snafu(display("Frame header version {version} is not supported"))]
+    UnsupportedHeaderVersion { backtrace: Backtrace, version: u8 },
+    #[snafu(display("{width} bit frame CRCs are not supported"))]
+    UnsupportedCrc { backtrace: Backtrace, width: u8 },
+    #[snafu(display("Unknown wire endianness {value}, expected 0 (big) or 1 (little)"))]
+    UnknownEndianness { backtrace: Backtrace, value: u8 },
 }
 
 #[derive(Snafu, Debug)]
@@ -133,35 +152,66 @@ pub fn attempt_load_elf(path: &Path) -> Result<ULogSystemInfo, ElfParseError> {
         .map(|(idx, header)| (idx, header.to_owned()))
         .context(NoULogSectionSnafu)?;
 
+    // Sections loaded in the memory of the target, they hold the strings sent by address
+    let loadable_sections = section_headers
+        .iter()
+        .filter(|x| {
+            x.sh_flags & elf::abi::SHF_ALLOC as u64 != 0 && x.sh_type == elf::abi::SHT_PROGBITS
+        })
+        .map(|x| (string_table.get(x.sh_name as usize).map(str::to_string), *x))
+        .collect::<Vec<_>>();
+
     // Unwrap the section into its byte representation, this is no longer streamed but ulog info should be fairly minimal and we don't really
     // have any other choice
-    let section_data = {
-        let (section_data, section_compression_header) =
-            elf_file.section_data(&section).context(ElfParseSnafu)?;
-        // Might implement this some day if really needed, can't imagine why it would though
-        if section_compression_header.is_some() {
-            return ULogSectionCompressedSnafu.fail();
-        }
-        section_data.to_owned()
+    let section_data =
+        read_section(&mut elf_file, &section).context(SectionReadSnafu { name: ".ulog" })?;
+
+    // On ARM the lowest bit of function addresses marks Thumb code
+    let thumb = elf_file.ehdr.e_machine == elf::abi::EM_ARM;
+
+    // Now we move to the symbol table, copied out so that the debug info can be read later on
+    let all_symbols = {
+        let (symbols, strings) = elf_file
+            .symbol_table()
+            .context(ElfParseSnafu)?
+            .context(NoSymbolTableSnafu)?;
+        symbols
+            .into_iter()
+            .map(|sym| {
+                strings
+                    .get(sym.st_name as usize)
+                    .context(ElfParseSnafu)
+                    .map(|name| (sym, name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?
     };
 
-    // Now we move to the symbol table
-    let (symbols, strings) = elf_file
-        .symbol_table()
-        .context(ElfParseSnafu)?
-        .context(NoSymbolTableSnafu)?;
+    // Functions and objects of the target, used to describe addresses it sends
+    let symbol_ranges = all_symbols
+        .iter()
+        .filter(|(sym, _)| {
+            !sym.is_undefined()
+                && matches!(sym.st_symtype(), elf::abi::STT_FUNC | elf::abi::STT_OBJECT)
+        })
+        .map(|(sym, name)| {
+            let mut address = sym.st_value;
+            if thumb && sym.st_symtype() == elf::abi::STT_FUNC {
+                address &= !1;
+            }
+            SymbolRange {
+                name: name.clone(),
+                address,
+                size: sym.st_size,
+            }
+        })
+        .collect::<Vec<_>>();
 
     // Get all symbols in the .ulog section, the rest is not our concern
-    let symbols = symbols
-        .into_iter()
-        .filter(|x| x.st_shndx == section_index as u16)
-        .map(|sym| {
-            strings
-                .get(sym.st_name as usize)
-                .context(ElfParseSnafu)
-                .map(|name| (sym, name))
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+    let symbols = all_symbols
+        .iter()
+        .filter(|x| x.0.st_shndx == section_index as u16)
+        .map(|(sym, name)| (sym.clone(), name.as_str()))
+        .collect::<Vec<_>>();
 
     // All ulog markers start with _(s|e)ulog, so filter that for efficient lookups
     let ulog_section_markers = symbols
@@ -284,21 +334,23 @@ pub fn attempt_load_elf(path: &Path) -> Result<ULogSystemInfo, ElfParseError> {
                     .build()
                 })?;
 
-                // Save that message into the struct for it
-                Ok(ULogMessage::new(
+                let location = Location {
+                    file: Arc::from(file),
+                    line: line
+                        .parse()
+                        .context(elf_symbol_parse_error::InvalidIntegerSnafu)?,
+                };
+                // Save that message into the struct for it, parsing the format string
+                let message = ULogMessage::new(
                     x.rel_pos as u16,
-                    // Parse the format string
-                    FormatString::from_string(format.clone()).with_context(|_| {
-                        elf_symbol_parse_error::TemplateParseSnafu { template: format }
-                    })?,
-                    Location {
-                        file: Arc::from(file),
-                        line: line
-                            .parse()
-                            .context(elf_symbol_parse_error::InvalidIntegerSnafu)?,
-                    },
+                    format.clone(),
+                    location,
                     get_severity_level(&x)?,
-                ))
+                )
+                .with_context(|_| elf_symbol_parse_error::TemplateParseSnafu {
+                    template: format,
+                })?;
+                Ok(message)
             })()
             .context(ElfSymbolParseSnafu {
                 name: x.name.to_string(),
@@ -308,6 +360,51 @@ pub fn attempt_load_elf(path: &Path) -> Result<ULogSystemInfo, ElfParseError> {
         .map_ok(|x| (x.id(), x))
         .collect::<Result<ULogMessageMap, _>>()?;
 
+    // Enum types and line tables from the debug info, format strings can refer to them. Walking
+    // the debug info is slow so it is only done when a message needs it, and since it only adds
+    // detail a file whose debug info can't be read still loads
+    let mut warnings = vec![];
+    let (needs_enums, needs_lines) = ulog_messages
+        .values()
+        .flat_map(|x| x.annotations().iter().flatten())
+        .fold((false, false), |(enums, lines), x| match x {
+            Annotation::Enum { .. } => (true, lines),
+            Annotation::Symbol { .. } => (enums, true),
+        });
+    let (enum_types, line_rows): (EnumTypes, _) = if needs_enums || needs_lines {
+        match read_debug_info(&mut elf_file, needs_enums, needs_lines) {
+            Ok(x) => x,
+            Err(err) => {
+                warnings.push(format!(
+                    "Enum names and source locations are unavailable: {}",
+                    err.iter_chain().map(|x| x.to_string()).join(": ")
+                ));
+                Default::default()
+            }
+        }
+    } else {
+        Default::default()
+    };
+    let symbolizer = Arc::new(Symbolizer::new(symbol_ranges, line_rows, thumb));
+    for message in ulog_messages.values_mut() {
+        message.resolve_annotations(&enum_types.types, &symbolizer);
+    }
+    // Names declared differently by several compilation units are left out of the enum types
+    let conflicts = ulog_messages
+        .values()
+        .flat_map(|x| x.annotations().iter().flatten())
+        .filter_map(|x| match x {
+            Annotation::Enum { name, .. } if enum_types.conflicts.contains(name) => Some(name),
+            _ => None,
+        })
+        .unique()
+        .sorted();
+    for name in conflicts {
+        warnings.push(format!(
+            "Enum type {name} is declared with different enumerators by several compilation units, its values are printed as numbers"
+        ));
+    }
+
     // Handling of arguments and saving those arguments into the approriate messages
     {
         let mut ulog_arguments = get_ulog_section("argument")?
@@ -415,6 +512,27 @@ pub fn attempt_load_elf(path: &Path) -> Result<ULogSystemInfo, ElfParseError> {
         }
     };
 
+    // The memory of the target is large, so it is only read when a message has a constant string
+    // argument sent by address. Those strings are printed as addresses when it can't be read
+    let needs_memory = ulog_messages
+        .values()
+        .flat_map(|x| x.arguments())
+        .any(|x| matches!(x, ULogArgument::CString { .. }));
+    let memory = if needs_memory {
+        match read_target_memory(&mut elf_file, loadable_sections) {
+            Ok(x) => x,
+            Err(err) => {
+                warnings.push(format!(
+                    "Constant strings sent by address are unavailable: {}",
+                    err.iter_chain().map(|x| x.to_string()).join(": ")
+                ));
+                TargetMemory::default()
+            }
+        }
+    } else {
+        TargetMemory::default()
+    };
+
     // Get the meta section for the system id
     let ulog_meta = get_ulog_section("meta")?;
     // Get the system id symbol
@@ -427,5 +545,92 @@ pub fn attempt_load_elf(path: &Path) -> Result<ULogSystemInfo, ElfParseError> {
         .parse_u16_at(&mut (system_id.symbol.st_value as usize), &section_data)
         .context(ElfParseSnafu)?;
 
-    Ok(ULogSystemInfo::new(ulog_strings, ulog_messages, system_id))
+    // Helper function to get the offset of an optional meta symbol
+    let get_meta_offset = |name: &str| {
+        ulog_meta
+            .iter()
+            .find(|x| x.name == name)
+            .map(|x| x.symbol.st_value as usize)
+    };
+
+    // Optional frame header extension, older systems don't declare it
+    let header_version = get_meta_offset("header_version")
+        .map(|mut offset| endianness.parse_u8_at(&mut offset, &section_data))
+        .transpose()
+        .context(ElfParseSnafu)?
+        .unwrap_or_default();
+    if header_version > MAX_HEADER_VERSION {
+        return UnsupportedHeaderVersionSnafu {
+            version: header_version,
+        }
+        .fail();
+    }
+    let tick_rate = get_meta_offset("tick_rate")
+        .map(|mut offset| endianness.parse_u32_at(&mut offset, &section_data))
+        .transpose()
+        .context(ElfParseSnafu)?;
+    // Optional CRC trailer, declared by its width in bits
+    let crc = get_meta_offset("crc")
+        .map(|mut offset| endianness.parse_u8_at(&mut offset, &section_data))
+        .transpose()
+        .context(ElfParseSnafu)?
+        .filter(|x| *x != 0)
+        .map(|width| FrameCrc::from_width(width).context(UnsupportedCrcSnafu { width }))
+        .transpose()?;
+    // Optional wire endianness, when missing the decoder default is used
+    let wire_endianness = get_meta_offset("endianness")
+        .map(|mut offset| endianness.parse_u8_at(&mut offset, &section_data))
+        .transpose()
+        .context(ElfParseSnafu)?
+        .map(|value| Endianness::from_meta(value).context(UnknownEndiannessSnafu { value }))
+        .transpose()?;
+
+    let mut system = ULogSystemInfo::new(
+        ulog_strings,
+        ulog_messages,
+        system_id,
+        ULogSystemMeta {
+            header_version,
+            tick_rate,
+            crc,
+            endianness: wire_endianness,
+        },
+        memory,
+    );
+    for warning in warnings {
+        system.add_warning(warning);
+    }
+    Ok(system)
+}
+
+/// Reads the sections loaded in the memory of the target, given with their names
+fn read_target_memory<R: Read + Seek>(
+    elf_file: &mut ElfStream<AnyEndian, R>,
+    sections: Vec<(Result<String, elf::ParseError>, SectionHeader)>,
+) -> Result<TargetMemory, ElfParseError> {
+    let mut memory = vec![];
+    for (name, header) in sections {
+        let name = name.context(ElfParseSnafu)?;
+        let data = read_section(elf_file, &header).context(SectionReadSnafu { name })?;
+        memory.push((header.sh_addr, data));
+    }
+    Ok(TargetMemory::new(memory))
+}
+
+/// Reads the enum types and the line tables of the debug info, each only if asked to
+fn read_debug_info<R: Read + Seek>(
+    elf_file: &mut ElfStream<AnyEndian, R>,
+    enums: bool,
+    lines: bool,
+) -> Result<(EnumTypes, Vec<LineRow>), DwarfError> {
+    let debug_info = DebugInfo::load(elf_file)?;
+    let enum_types = match enums {
+        true => debug_info.enum_types()?,
+        false => EnumTypes::default(),
+    };
+    let line_rows = match lines {
+        true => debug_info.line_rows()?,
+        false => vec![],
+    };
+    Ok((enum_types, line_rows))
 }
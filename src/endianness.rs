@@ -0,0 +1,78 @@
+use byteorder::{BE, LE, ReadBytesExt};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+
+/// Byte order of the multi byte values a system puts on the wire
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Endianness {
+    /// Original wire format
+    #[default]
+    Big,
+    Little,
+}
+
+impl Endianness {
+    /// Gets the endianness declared by the `endianness` meta symbol
+    pub fn from_meta(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Endianness::Big),
+            1 => Some(Endianness::Little),
+            _ => None,
+        }
+    }
+
+    pub fn read_u16(&self, reader: &mut impl Read) -> std::io::Result<u16> {
+        match self {
+            Endianness::Big => reader.read_u16::<BE>(),
+            Endianness::Little => reader.read_u16::<LE>(),
+        }
+    }
+
+    pub fn read_u32(&self, reader: &mut impl Read) -> std::io::Result<u32> {
+        match self {
+            Endianness::Big => reader.read_u32::<BE>(),
+            Endianness::Little => reader.read_u32::<LE>(),
+        }
+    }
+
+    pub fn read_f32(&self, reader: &mut impl Read) -> std::io::Result<f32> {
+        match self {
+            Endianness::Big => reader.read_f32::<BE>(),
+            Endianness::Little => reader.read_f32::<LE>(),
+        }
+    }
+
+    pub fn read_f64(&self, reader: &mut impl Read) -> std::io::Result<f64> {
+        match self {
+            Endianness::Big => reader.read_f64::<BE>(),
+            Endianness::Little => reader.read_f64::<LE>(),
+        }
+    }
+
+    /// Reads a signed integer of `size` bytes, from 1 to 8, and sign extends it
+    pub fn read_int(&self, reader: &mut impl Read, size: usize) -> std::io::Result<i64> {
+        match self {
+            Endianness::Big => reader.read_int::<BE>(size),
+            Endianness::Little => reader.read_int::<LE>(size),
+        }
+    }
+
+    /// Reads an unsigned integer of `size` bytes, from 1 to 8
+    pub fn read_uint(&self, reader: &mut impl Read, size: usize) -> std::io::Result<u64> {
+        match self {
+            Endianness::Big => reader.read_uint::<BE>(size),
+            Endianness::Little => reader.read_uint::<LE>(size),
+        }
+    }
+}
+
+impl Display for Endianness {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endianness::Big => write!(f, "big endian"),
+            Endianness::Little => write!(f, "little endian"),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs::{File, Metadata};
+use std::io::Read;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+/// Delay between two checks for new data
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Reader with `tail -f` semantics: reaching the end of the file waits for more data instead of
+/// returning EOF. When the file is truncated or replaced, the path is reopened and read from the
+/// start. A single error is returned when that happens so that any partially read frame gets
+/// dropped instead of being glued to the new data
+pub struct FollowReader {
+    path: PathBuf,
+    file: File,
+    /// Number of bytes read from the current file
+    position: u64,
+    /// Identity of the current file, used to detect when the path gets replaced
+    id: Option<u64>,
+}
+
+impl FollowReader {
+    pub fn open(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        let file = File::open(&path)?;
+        let id = file_id(&file.metadata()?);
+        Ok(Self {
+            path,
+            file,
+            position: 0,
+            id,
+        })
+    }
+
+    /// Checks whether the file at the path is no longer the one being read
+    fn replaced(&self) -> bool {
+        let Ok(metadata) = std::fs::metadata(&self.path) else {
+            // The path is missing while the file is being rotated, keep waiting on the current one
+            return false;
+        };
+        metadata.len() < self.position || file_id(&metadata) != self.id
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let size = self.file.read(buf)?;
+            if size > 0 || buf.is_empty() {
+                self.position += size as u64;
+                return Ok(size);
+            }
+
+            if self.replaced() {
+                *self = Self::open(&self.path)?;
+                return Err(std::io::Error::other(format!(
+                    "{} was truncated or replaced, reading it from the start",
+                    self.path.display()
+                )));
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// Gets an identifier unique to a file on its filesystem
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+/// Gets an identifier unique to a file on its filesystem, unavailable on this platform so only
+/// truncation can be detected
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::follow_reader::FollowReader;
+    use std::fs::{File, OpenOptions};
+    use std::io::{Read, Write};
+
+    fn read(reader: &mut FollowReader) -> std::io::Result<Vec<u8>> {
+        let mut buf = [0; 16];
+        let size = reader.read(&mut buf)?;
+        Ok(buf[..size].to_vec())
+    }
+
+    #[test]
+    fn follow() {
+        let dir = std::env::temp_dir().join(format!("ulog-follow-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("stream.bin");
+        std::fs::write(&path, b"ab").unwrap();
+        let mut reader = FollowReader::open(&path).unwrap();
+        assert_eq!(read(&mut reader).unwrap(), b"ab");
+
+        // Appended data is read as it comes
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"cd").unwrap();
+        assert_eq!(read(&mut reader).unwrap(), b"cd");
+
+        // Truncation restarts from the start, after an error dropping the partial frame
+        file.set_len(0).unwrap();
+        file.write_all(b"x").unwrap();
+        assert!(read(&mut reader).is_err());
+        assert_eq!(read(&mut reader).unwrap(), b"x");
+
+        // So does replacing the file, once the old one has nothing left to read
+        std::fs::rename(&path, dir.join("stream.bin.1")).unwrap();
+        file.write_all(b"y").unwrap();
+        File::create(&path).unwrap().write_all(b"new").unwrap();
+        assert_eq!(read(&mut reader).unwrap(), b"y");
+        assert!(read(&mut reader).is_err());
+        assert_eq!(read(&mut reader).unwrap(), b"new");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,88 @@
+use crate::endianness::Endianness;
+use crc::{CRC_16_IBM_3740, CRC_32_ISO_HDLC, Crc};
+use std::fmt::{Display, Formatter};
+
+const CRC_16: Crc<u16> = Crc::<u16>::new(&CRC_16_IBM_3740);
+const CRC_32: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+/// CRC trailer a system appends to its frames, computed over the whole decoded frame before it and
+/// stored in the wire endianness of the system
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FrameCrc {
+    /// CRC-16/CCITT-FALSE
+    Crc16,
+    /// CRC-32 as used by zlib and Ethernet
+    Crc32,
+}
+
+impl FrameCrc {
+    /// Gets the CRC declared by the `crc` meta symbol, which holds its width in bits
+    pub fn from_width(width: u8) -> Option<Self> {
+        match width {
+            16 => Some(FrameCrc::Crc16),
+            32 => Some(FrameCrc::Crc32),
+            _ => None,
+        }
+    }
+
+    /// Width of the CRC in bits, as declared by the `crc` meta symbol
+    pub fn width(&self) -> u8 {
+        match self {
+            FrameCrc::Crc16 => 16,
+            FrameCrc::Crc32 => 32,
+        }
+    }
+
+    /// Size of the trailer in bytes
+    pub fn size(&self) -> usize {
+        match self {
+            FrameCrc::Crc16 => 2,
+            FrameCrc::Crc32 => 4,
+        }
+    }
+
+    pub fn compute(&self, data: &[u8]) -> u32 {
+        match self {
+            FrameCrc::Crc16 => CRC_16.checksum(data) as u32,
+            FrameCrc::Crc32 => CRC_32.checksum(data),
+        }
+    }
+
+    /// Splits a frame into the data the CRC covers and the CRC it was sent with, returns None if
+    /// the frame is too short to hold one
+    pub fn split<'a>(&self, frame: &'a [u8], endianness: Endianness) -> Option<(&'a [u8], u32)> {
+        let (data, mut trailer) = frame.split_at_checked(frame.len().checked_sub(self.size())?)?;
+        let crc = endianness.read_uint(&mut trailer, self.size()).ok()?;
+        Some((data, crc as u32))
+    }
+}
+
+impl Display for FrameCrc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FrameCrc::Crc16 => write!(f, "CRC-16"),
+            FrameCrc::Crc32 => write!(f, "CRC-32"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::endianness::Endianness;
+    use crate::frame_crc::FrameCrc;
+
+    #[test]
+    fn check() {
+        assert_eq!(FrameCrc::Crc16.compute(b"123456789"), 0x29B1);
+        assert_eq!(FrameCrc::Crc32.compute(b"123456789"), 0xCBF43926);
+        assert_eq!(
+            FrameCrc::Crc16.split(b"abc\x12\x34", Endianness::Big),
+            Some((&b"abc"[..], 0x1234))
+        );
+        assert_eq!(
+            FrameCrc::Crc16.split(b"abc\x12\x34", Endianness::Little),
+            Some((&b"abc"[..], 0x3412))
+        );
+        assert_eq!(FrameCrc::Crc32.split(b"abc", Endianness::Big), None);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::endianness::Endianness;
+use byteorder::ReadBytesExt;
+use serde::Serialize;
+use std::io::BufRead;
+
+/// Latest frame header extension version this decoder understands
+pub const MAX_HEADER_VERSION: u8 = 1;
+
+/// Flag set when the extension carries a tick counter
+const FLAG_TICKS: u8 = 1 << 0;
+/// Flag set when the extension carries a sequence counter
+const FLAG_SEQUENCE: u8 = 1 << 1;
+
+/// Optional fields carried by the frame header extension.
+///
+/// Version 1 of the extension follows the message id and is made of a flag byte followed by
+/// every field whose flag is set, in order and in the wire endianness of the system:
+/// - bit 0: device tick counter (u32)
+/// - bit 1: sequence counter (u16)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameHeaderExtension {
+    /// Value of the device tick counter when the entry was emitted
+    pub ticks: Option<u32>,
+    /// Sequence number of the entry, incremented for every entry the system emits
+    pub sequence: Option<u16>,
+}
+
+impl FrameHeaderExtension {
+    /// Reads the extension for a system emitting header version `version`
+    pub fn read(
+        reader: &mut impl BufRead,
+        version: u8,
+        endianness: Endianness,
+    ) -> std::io::Result<Self> {
+        if version == 0 {
+            return Ok(Self::default());
+        }
+
+        let flags = reader.read_u8()?;
+        let ticks = if flags & FLAG_TICKS != 0 {
+            Some(endianness.read_u32(reader)?)
+        } else {
+            None
+        };
+        let sequence = if flags & FLAG_SEQUENCE != 0 {
+            Some(endianness.read_u16(reader)?)
+        } else {
+            None
+        };
+        Ok(Self { ticks, sequence })
+    }
+}
+
+/// Device side time at which an entry was emitted
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DeviceTimestamp {
+    /// Tick count since the device started, with wrap arounds of the 32 bit counter accounted for
+    pub ticks: u64,
+    /// Time in seconds since the device started, when the tick rate is known
+    pub seconds: Option<f64>,
+}
+
+/// Reconstructs a monotonic device time from a wrapping 32 bit tick counter
+#[derive(Debug, Clone, Default)]
+pub struct DeviceClock {
+    /// Last tick counter value seen
+    last_ticks: Option<u32>,
+    /// Number of times the counter wrapped around
+    wraps: u64,
+}
+
+impl DeviceClock {
+    /// Accounts for a new tick counter value and returns the reconstructed device time
+    pub fn update(&mut self, ticks: u32, tick_rate: Option<u32>) -> DeviceTimestamp {
+        let mut wraps = self.wraps;
+        match self.last_ticks {
+            // Going backwards from the top of the range to the bottom is a wrap around
+            Some(last_ticks) if last_ticks >= 0xC000_0000 && ticks < 0x4000_0000 => {
+                self.wraps += 1;
+                wraps = self.wraps;
+                self.last_ticks = Some(ticks);
+            }
+            // Going from the bottom of the range to the top is an entry from before the last
+            // wrap around arriving late
+            Some(last_ticks) if last_ticks < 0x4000_0000 && ticks >= 0xC000_0000 => {
+                wraps = self.wraps.saturating_sub(1);
+            }
+            // Going slightly backwards is an entry arriving late too
+            Some(last_ticks) if ticks < last_ticks && last_ticks - ticks < 0x4000_0000 => {}
+            // Anything further back means the device restarted and the counter started over
+            Some(last_ticks) if ticks < last_ticks => {
+                self.wraps = 0;
+                wraps = 0;
+                self.last_ticks = Some(ticks);
+            }
+            _ => self.last_ticks = Some(ticks),
+        }
+
+        let ticks = (wraps << 32) | ticks as u64;
+        DeviceTimestamp {
+            ticks,
+            seconds: tick_rate
+                .filter(|x| *x != 0)
+                .map(|rate| ticks as f64 / rate as f64),
+        }
+    }
+}
+
+/// Detects lost entries from gaps in a wrapping 16 bit sequence counter
+#[derive(Debug, Clone, Default)]
+pub struct SequenceTracker {
+    /// Last sequence number seen
+    last: Option<u16>,
+}
+
+impl SequenceTracker {
+    /// Accounts for a new sequence number and returns how many entries were lost right before it
+    pub fn update(&mut self, sequence: u16) -> u32 {
+        let lost = match self.last {
+            None => 0,
+            // Counting forward, possibly across a wrap around. A repeated number gives u16::MAX
+            // and is a duplicate instead
+            Some(last) if sequence.wrapping_sub(last).wrapping_sub(1) < 0x8000 => {
+                sequence.wrapping_sub(last).wrapping_sub(1) as u32
+            }
+            // Going far back means the device restarted, the first entries since may be missing
+            // so it can start anywhere
+            Some(_) => 0,
+        };
+        self.last = Some(sequence);
+        lost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::endianness::Endianness;
+    use crate::frame_header::{DeviceClock, FrameHeaderExtension, SequenceTracker};
+
+    #[test]
+    fn extension() {
+        let ext = FrameHeaderExtension::read(
+            &mut &[0x01, 0x00, 0x00, 0x01, 0x00][..],
+            1,
+            Endianness::Big,
+        )
+        .unwrap();
+        assert_eq!(ext.ticks, Some(0x100));
+        let ext = FrameHeaderExtension::read(&mut &[0x00][..], 1, Endianness::Big).unwrap();
+        assert_eq!(ext.ticks, None);
+        let ext =
+            FrameHeaderExtension::read(&mut &[0x02, 0x12, 0x34][..], 1, Endianness::Big).unwrap();
+        assert_eq!(ext.sequence, Some(0x1234));
+    }
+
+    #[test]
+    fn sequence_gaps() {
+        let mut sequences = SequenceTracker::default();
+        assert_eq!(sequences.update(0xFFFE), 0);
+        assert_eq!(sequences.update(0xFFFF), 0);
+        // Wrapping around loses nothing, skipping numbers does
+        assert_eq!(sequences.update(0x0000), 0);
+        assert_eq!(sequences.update(0x000D), 12);
+        // A device restart isn't a loss
+        assert_eq!(sequences.update(0x0000), 0);
+
+        // Entries lost right before a wrap around are still counted
+        let mut sequences = SequenceTracker::default();
+        assert_eq!(sequences.update(0xFFF0), 0);
+        assert_eq!(sequences.update(0x0000), 15);
+    }
+
+    #[test]
+    fn sequence_restart() {
+        let mut sequences = SequenceTracker::default();
+        assert_eq!(sequences.update(500), 0);
+        // Restarted, the entries numbered 0 and 1 never arrived
+        assert_eq!(sequences.update(2), 0);
+        assert_eq!(sequences.update(4), 1);
+        // Duplicates aren't losses either
+        assert_eq!(sequences.update(4), 0);
+        assert_eq!(sequences.update(5), 0);
+        // Far enough forward to be mistaken for going backwards, another restart
+        assert_eq!(sequences.update(0xFFFE), 0);
+        // Gaps across a wrap around are still losses
+        assert_eq!(sequences.update(0x0001), 2);
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut clock = DeviceClock::default();
+        assert_eq!(clock.update(0xFFFF_FF00, Some(1000)).ticks, 0xFFFF_FF00);
+        let time = clock.update(0x0000_0010, Some(1000));
+        assert_eq!(time.ticks, 0x1_0000_0010);
+        assert_eq!(time.seconds, Some(0x1_0000_0010u64 as f64 / 1000.0));
+        // Late entries keep the wrap count of the time they were emitted
+        assert_eq!(clock.update(0x0000_0008, None).ticks, 0x1_0000_0008);
+        assert_eq!(clock.update(0xFFFF_FFF0, None).ticks, 0xFFFF_FFF0);
+        assert_eq!(clock.update(0x0000_0020, None).ticks, 0x1_0000_0020);
+        // A device restart resets the counter instead of wrapping
+        assert_eq!(clock.update(0x8000_0000, None).ticks, 0x1_8000_0000);
+        assert_eq!(clock.update(0x0000_0005, None).ticks, 5);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::decoder::FrameRecognizer;
+use crate::framing::Framing;
+use crate::mixed_stream::{ConsoleChunk, read_console_chunk};
+use crate::timestamp::ReceiveTimestamp;
+use std::io::BufRead;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A frame as it was read from a stream
+#[derive(Debug, Clone)]
+pub struct RawFrame {
+    /// Frame bytes, without the delimiter
+    pub data: Vec<u8>,
+    /// Time at which the end of the frame was received
+    pub timestamp: ReceiveTimestamp,
+    /// Tag of the stream the frame was read from, when a source has several streams
+    pub source: Option<Arc<str>>,
+    /// Whether the data is a line of console text rather than a frame, see
+    /// [`FrameReader::with_console_text`]
+    pub text: bool,
+}
+
+/// Iterator over the raw frames of a stream, rzcobs frames delimited with a null byte unless
+/// another framing is set. Delimiters and length prefixes are not included in the returned frames
+pub struct FrameReader<R: BufRead> {
+    /// Underlying stream
+    reader: R,
+    /// How frames are delimited in the stream
+    framing: Framing,
+    /// Buffer holding the last frame read, including its delimiter
+    buf: Vec<u8>,
+    /// Instant the monotonic timestamps are relative to
+    start: Instant,
+    /// Tag given to the frames read
+    source: Option<Arc<str>>,
+    /// Recognizer of the frames, set when console text is expected between them
+    console_text: Option<FrameRecognizer>,
+    /// Frame read along with the last line of console text, to return next
+    pending: Option<RawFrame>,
+}
+
+impl<R: BufRead> FrameReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_start(reader, Instant::now())
+    }
+
+    /// Creates a frame reader whose monotonic timestamps are relative to `start`
+    pub fn with_start(reader: R, start: Instant) -> Self {
+        Self {
+            reader,
+            framing: Framing::default(),
+            buf: vec![],
+            start,
+            source: None,
+            console_text: None,
+            pending: None,
+        }
+    }
+
+    /// Reads frames using `framing` instead of rzcobs
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Expects lines of plain console text between frames, such as bootloader messages, and
+    /// returns them as text frames when `console_text` is set. Only framings with a delimiter can
+    /// tell them apart, and text without a newline is only split off the frames `console_text`
+    /// recognizes, see [`read_console_chunk`]
+    pub fn with_console_text(mut self, console_text: Option<FrameRecognizer>) -> Self {
+        self.console_text = console_text;
+        self
+    }
+
+    /// Tags all the frames read with `source`
+    pub fn tagged(mut self, source: Arc<str>) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Bytes of the last frame read, as they were received
+    pub fn last_frame(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn raw_frame(&self, range: Range<usize>, text: bool) -> RawFrame {
+        RawFrame {
+            data: self.buf[range].to_vec(),
+            // Taken as soon as the frame is read so that decoding time doesn't skew it
+            timestamp: ReceiveTimestamp::now(self.start),
+            source: self.source.clone(),
+            text,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for FrameReader<R> {
+    type Item = std::io::Result<RawFrame>;
+
+    /// Reads the next frame, returns None when EOF is reached
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(frame) = self.pending.take() {
+            return Some(Ok(frame));
+        }
+
+        let chunk = match &self.console_text {
+            Some(recognizer) => read_console_chunk(recognizer, &mut self.reader, &mut self.buf),
+            None => (self.framing.read_frame(&mut self.reader, &mut self.buf))
+                .map(|x| x.map(ConsoleChunk::Frame)),
+        };
+        let (range, text) = match chunk {
+            Ok(Some(ConsoleChunk::Text(range))) => (range, true),
+            Ok(Some(ConsoleChunk::Frame(range))) => (range, false),
+            Ok(Some(ConsoleChunk::Prompt { text, frame })) => {
+                self.pending = Some(self.raw_frame(frame, false));
+                (text, true)
+            }
+            Ok(None) => return None,
+            Err(err) => return Some(Err(err)),
+        };
+        Some(Ok(self.raw_frame(range, text)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frame_reader::FrameReader;
+
+    #[test]
+    fn frames() {
+        let frames = FrameReader::new(&b"\x01\x02\x00\x03\x00"[..])
+            .map(|x| x.map(|x| x.data))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(frames, vec![vec![1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn incomplete() {
+        let mut frames = FrameReader::new(&b"\x01\x00\x02"[..]);
+        assert!(frames.next().unwrap().is_ok());
+        assert!(frames.next().unwrap().is_err());
+        assert!(frames.next().is_none());
+    }
+}
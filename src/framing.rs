@@ -0,0 +1,265 @@
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, ErrorKind};
+use std::ops::Range;
+
+/// SLIP special bytes
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// How frames are delimited in a stream and encoded
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Framing {
+    /// Reverse zero compressing COBS, delimited with a null byte
+    #[default]
+    Rzcobs,
+    /// Plain COBS, delimited with a null byte
+    Cobs,
+    /// SLIP (RFC 1055), delimited with an END byte
+    Slip,
+    /// Big endian u16 length followed by the unencoded payload
+    LengthPrefixed,
+    /// Unencoded payload, every read of the stream holds exactly one frame. Meant for transports
+    /// that already do the framing
+    Unframed,
+}
+
+impl Framing {
+    /// Byte ending every frame, for framings that have one
+    pub fn delimiter(&self) -> Option<u8> {
+        match self {
+            Framing::Rzcobs | Framing::Cobs => Some(0x00),
+            Framing::Slip => Some(SLIP_END),
+            Framing::LengthPrefixed | Framing::Unframed => None,
+        }
+    }
+
+    /// Reads the next frame of `reader` into `buf`, which then holds the frame as it was received.
+    /// Returns the range of the frame in `buf`, without its delimiter or length, or None once the
+    /// stream has ended
+    pub fn read_frame<R: BufRead>(
+        &self,
+        reader: &mut R,
+        buf: &mut Vec<u8>,
+    ) -> std::io::Result<Option<Range<usize>>> {
+        buf.clear();
+        match self {
+            Framing::Rzcobs | Framing::Cobs | Framing::Slip => loop {
+                // Unwrap is safe here because these framings all have a delimiter
+                let delimiter = self.delimiter().unwrap();
+                retry_timeouts(|| reader.read_until(delimiter, buf))?;
+
+                // Nothing left to read
+                if buf.is_empty() {
+                    return Ok(None);
+                }
+                // The stream ended in the middle of a frame, there is no point in decoding it
+                if buf.last() != Some(&delimiter) {
+                    return Err(incomplete_frame());
+                }
+                // SLIP senders usually start frames with an END byte as well, skip the empty frames
+                if *self == Framing::Slip && buf.len() == 1 {
+                    buf.clear();
+                    continue;
+                }
+                return Ok(Some(0..buf.len() - 1));
+            },
+            Framing::LengthPrefixed => {
+                if !read_exact(reader, buf, 2)? {
+                    return Ok(None);
+                }
+                let length = u16::from_be_bytes([buf[0], buf[1]]) as usize;
+                if !read_exact(reader, buf, length)? {
+                    return Err(incomplete_frame());
+                }
+                Ok(Some(2..buf.len()))
+            }
+            Framing::Unframed => {
+                let data = fill_buf(reader)?;
+                buf.extend_from_slice(data);
+                reader.consume(buf.len());
+                Ok((!buf.is_empty()).then_some(0..buf.len()))
+            }
+        }
+    }
+
+    /// Splits a datagram, whose end also ends its last frame, into frames
+    pub fn split<'a>(&self, data: &'a [u8]) -> Vec<&'a [u8]> {
+        match self {
+            Framing::Rzcobs | Framing::Cobs | Framing::Slip => {
+                // Unwrap is safe here because these framings all have a delimiter
+                let delimiter = self.delimiter().unwrap();
+                data.split(|x| *x == delimiter)
+                    .filter(|x| !x.is_empty())
+                    .collect()
+            }
+            Framing::LengthPrefixed => {
+                let mut frames = vec![];
+                let mut data = data;
+                while let [a, b, rest @ ..] = data {
+                    // A truncated last frame is kept as is, it will fail to decode
+                    let length = (u16::from_be_bytes([*a, *b]) as usize).min(rest.len());
+                    let (frame, rest) = rest.split_at(length);
+                    frames.push(frame);
+                    data = rest;
+                }
+                frames
+            }
+            Framing::Unframed => vec![data],
+        }
+    }
+
+    /// Removes the encoding of a frame, returns None if the frame isn't valid
+    pub fn decode(&self, frame: &[u8]) -> Option<Vec<u8>> {
+        match self {
+            Framing::Rzcobs => rzcobs::decode(frame).ok(),
+            Framing::Cobs => cobs_decode(frame),
+            Framing::Slip => slip_decode(frame),
+            Framing::LengthPrefixed | Framing::Unframed => Some(frame.to_vec()),
+        }
+    }
+}
+
+impl Display for Framing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Framing::Rzcobs => write!(f, "rzcobs"),
+            Framing::Cobs => write!(f, "COBS"),
+            Framing::Slip => write!(f, "SLIP"),
+            Framing::LengthPrefixed => write!(f, "length-prefixed"),
+            Framing::Unframed => write!(f, "unframed"),
+        }
+    }
+}
+
+pub(crate) fn incomplete_frame() -> std::io::Error {
+    std::io::Error::new(
+        ErrorKind::UnexpectedEof,
+        "Incomplete frame at the end of the stream",
+    )
+}
+
+/// Calls `read` until it fails with something else than a timeout
+fn retry_timeouts<T>(mut read: impl FnMut() -> std::io::Result<T>) -> std::io::Result<T> {
+    loop {
+        match read() {
+            // We ignore timeout errors and silently try again
+            Err(err) if err.kind() == ErrorKind::TimedOut => continue,
+            result => return result,
+        }
+    }
+}
+
+/// Fills the buffer of `reader`, retrying on timeouts
+pub(crate) fn fill_buf<R: BufRead>(reader: &mut R) -> std::io::Result<&[u8]> {
+    retry_timeouts(|| reader.fill_buf().map(|_| ()))?;
+    // The data is buffered now, this doesn't read anything
+    reader.fill_buf()
+}
+
+/// Appends exactly `length` bytes of `reader` to `buf`. Returns false if the stream ended before
+/// that, in which case `buf` holds whatever could be read
+fn read_exact<R: BufRead>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    length: usize,
+) -> std::io::Result<bool> {
+    let end = buf.len() + length;
+    while buf.len() < end {
+        let data = fill_buf(reader)?;
+        if data.is_empty() {
+            return Ok(false);
+        }
+        let size = data.len().min(end - buf.len());
+        buf.extend_from_slice(&data[..size]);
+        reader.consume(size);
+    }
+    Ok(true)
+}
+
+/// Decodes a COBS frame without its delimiter
+fn cobs_decode(frame: &[u8]) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(frame.len());
+    let mut rest = frame;
+    while let [code, tail @ ..] = rest {
+        if *code == 0 || *code as usize - 1 > tail.len() {
+            return None;
+        }
+        let length = *code as usize - 1;
+        data.extend_from_slice(&tail[..length]);
+        rest = &tail[length..];
+        // Every block but the longest ones and the last one is followed by an encoded zero
+        if *code != 0xFF && !rest.is_empty() {
+            data.push(0x00);
+        }
+    }
+    Some(data)
+}
+
+/// Decodes a SLIP frame without its END delimiter
+fn slip_decode(frame: &[u8]) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(frame.len());
+    let mut bytes = frame.iter();
+    while let Some(byte) = bytes.next() {
+        match *byte {
+            SLIP_ESC => match bytes.next() {
+                Some(&SLIP_ESC_END) => data.push(SLIP_END),
+                Some(&SLIP_ESC_ESC) => data.push(SLIP_ESC),
+                _ => return None,
+            },
+            byte => data.push(byte),
+        }
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framing::Framing;
+
+    #[test]
+    fn decode() {
+        assert_eq!(
+            Framing::Cobs.decode(&[0x03, 0x11, 0x22, 0x02, 0x33]),
+            Some(vec![0x11, 0x22, 0x00, 0x33])
+        );
+        assert_eq!(Framing::Cobs.decode(&[0x01, 0x01]), Some(vec![0x00]));
+        assert_eq!(Framing::Cobs.decode(&[0x05, 0x11]), None);
+        assert_eq!(
+            Framing::Slip.decode(&[0x01, 0xDB, 0xDC, 0xDB, 0xDD]),
+            Some(vec![0x01, 0xC0, 0xDB])
+        );
+        assert_eq!(Framing::Slip.decode(&[0xDB, 0x01]), None);
+    }
+
+    #[test]
+    fn read() {
+        let mut buf = vec![];
+        let mut reader = &b"\x00\x02\x01\x02\x00\x05\x03"[..];
+        let frame = Framing::LengthPrefixed.read_frame(&mut reader, &mut buf);
+        assert_eq!(frame.unwrap(), Some(2..4));
+        assert_eq!(&buf[2..4], [0x01, 0x02]);
+        assert!(
+            Framing::LengthPrefixed
+                .read_frame(&mut reader, &mut buf)
+                .is_err()
+        );
+
+        let mut reader = &b"\xC0\x01\xC0"[..];
+        let frame = Framing::Slip.read_frame(&mut reader, &mut buf);
+        assert_eq!(frame.unwrap(), Some(0..1));
+        assert_eq!(
+            Framing::Slip.read_frame(&mut reader, &mut buf).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            Framing::LengthPrefixed.split(b"\x00\x01\x0A\x00\x02\x0B"),
+            vec![&[0x0A][..], &[0x0B][..]]
+        );
+    }
+}
@@ -0,0 +1,34 @@
+pub mod catalog;
+pub mod decoded_entry;
+pub mod decoder;
+pub mod dictionary;
+pub mod dwarf;
+pub mod elf;
+pub mod endianness;
+pub mod follow_reader;
+pub mod frame_crc;
+pub mod frame_header;
+pub mod frame_reader;
+pub mod framing;
+pub mod lint;
+pub mod location;
+pub mod map_diff;
+pub mod mixed_stream;
+pub mod placeholder;
+pub mod section;
+pub mod serial_source;
+pub mod severity;
+pub mod source;
+mod splitter;
+pub mod symbolizer;
+pub mod target_memory;
+pub mod tcp_source;
+#[cfg(test)]
+mod test_support;
+pub mod timestamp;
+pub mod udp_source;
+pub mod ulog_argument;
+pub mod ulog_message;
+pub mod ulog_string;
+pub mod ulog_system_info;
+pub mod util;
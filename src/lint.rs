@@ -0,0 +1,358 @@
+use crate::location::Location;
+use crate::placeholder::{Annotation, placeholders};
+use crate::ulog_argument::ULogArgument;
+use crate::ulog_system_info::ULogSystemInfo;
+use dyf::{FormatString, Formatter};
+use itertools::Itertools;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter as FmtFormatter};
+use std::sync::Arc;
+
+/// Problem found in a uLog map, which would make entries fail to decode or print wrongly
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LintIssue {
+    /// The format string of a message doesn't have one placeholder per argument
+    ArgumentCount {
+        id: u16,
+        #[serde(flatten)]
+        location: Location,
+        placeholders: usize,
+        arguments: usize,
+    },
+    /// The spec of a placeholder can't format the type of its argument, such as `{:x}` on a float
+    IncompatibleSpec {
+        id: u16,
+        #[serde(flatten)]
+        location: Location,
+        /// Position of the placeholder in the format string, from 0
+        index: usize,
+        placeholder: String,
+        argument: String,
+    },
+    /// The annotation of a placeholder only applies to integers, such as `{:sym}` on a float
+    IncompatibleAnnotation {
+        id: u16,
+        #[serde(flatten)]
+        location: Location,
+        /// Position of the placeholder in the format string, from 0
+        index: usize,
+        annotation: String,
+        argument: String,
+    },
+    /// A placeholder refers to an enum type missing from the debug info, or declared differently
+    /// by several compilation units, its values are printed as numbers
+    UnknownEnum {
+        id: u16,
+        #[serde(flatten)]
+        location: Location,
+        /// Position of the placeholder in the format string, from 0
+        index: usize,
+        name: String,
+    },
+    /// Messages sharing a location and a format string, their arguments can't be told apart
+    DuplicateMessage {
+        ids: Vec<u16>,
+        #[serde(flatten)]
+        location: Location,
+        format: String,
+    },
+    /// String declared in a file without any message taking a uLog string argument. Only a
+    /// warning, since strings can be printed by messages of other files
+    UnusedString {
+        id: u16,
+        #[serde(flatten)]
+        location: Location,
+        string: Arc<String>,
+    },
+}
+
+impl LintIssue {
+    /// Whether the issue may be a false positive rather than a mistake, warnings don't make
+    /// [`crate::decoder::Decoder::load_map`] refuse a map in strict mode
+    pub fn is_warning(&self) -> bool {
+        matches!(self, LintIssue::UnusedString { .. })
+    }
+}
+
+impl Display for LintIssue {
+    fn fmt(&self, f: &mut FmtFormatter<'_>) -> std::fmt::Result {
+        match self {
+            LintIssue::ArgumentCount {
+                id,
+                location,
+                placeholders,
+                arguments,
+            } => write!(
+                f,
+                "Message {id} ({}:{}) has {placeholders} placeholders but {arguments} arguments",
+                location.file, location.line
+            ),
+            LintIssue::IncompatibleSpec {
+                id,
+                location,
+                index,
+                placeholder,
+                argument,
+            } => write!(
+                f,
+                "Placeholder {index} {placeholder} of message {id} ({}:{}) can't format its {argument} argument",
+                location.file, location.line
+            ),
+            LintIssue::IncompatibleAnnotation {
+                id,
+                location,
+                index,
+                annotation,
+                argument,
+            } => write!(
+                f,
+                "Placeholder {index} of message {id} ({}:{}) is annotated with {annotation}, which only applies to integers, not to its {argument} argument",
+                location.file, location.line
+            ),
+            LintIssue::UnknownEnum {
+                id,
+                location,
+                index,
+                name,
+            } => write!(
+                f,
+                "Placeholder {index} of message {id} ({}:{}) refers to the enum type {name}, which can't be resolved from the debug info",
+                location.file, location.line
+            ),
+            LintIssue::DuplicateMessage {
+                ids,
+                location,
+                format,
+            } => write!(
+                f,
+                "Messages {} ({}:{}) share the format string {format:?}, their arguments can't be told apart",
+                ids.iter().join(", "),
+                location.file,
+                location.line
+            ),
+            LintIssue::UnusedString {
+                id,
+                location,
+                string,
+            } => write!(
+                f,
+                "String {id} {string:?} ({}:{}) is unused, no message of its file takes a uLog string",
+                location.file, location.line
+            ),
+        }
+    }
+}
+
+/// Checks a uLog map for issues the loader lets through, sorted by message then string id
+pub fn lint_system(system: &ULogSystemInfo) -> Vec<LintIssue> {
+    let mut issues = vec![];
+    let messages = system
+        .messages()
+        .values()
+        .sorted_by_key(|x| x.id())
+        .collect::<Vec<_>>();
+
+    for message in &messages {
+        let placeholders = placeholders(message.format());
+        let arguments = message.arguments();
+        if placeholders.len() != arguments.len() {
+            issues.push(LintIssue::ArgumentCount {
+                id: message.id(),
+                location: message.location().clone(),
+                placeholders: placeholders.len(),
+                arguments: arguments.len(),
+            });
+        }
+
+        // Format a value of the argument type with each placeholder on its own, as the decoder
+        // would
+        for (index, (placeholder, argument)) in placeholders.iter().zip(arguments).enumerate() {
+            if !formats(&placeholder.text, argument) {
+                issues.push(LintIssue::IncompatibleSpec {
+                    id: message.id(),
+                    location: message.location().clone(),
+                    index,
+                    placeholder: placeholder.text.clone(),
+                    argument: argument.type_name(),
+                });
+            }
+        }
+
+        // Annotations were stripped from the placeholders, they are checked on their own
+        let annotations = message.annotations().iter().zip(arguments).enumerate();
+        for (index, (annotation, argument)) in annotations {
+            let Some(annotation) = annotation else {
+                continue;
+            };
+            if !is_integer(argument) {
+                issues.push(LintIssue::IncompatibleAnnotation {
+                    id: message.id(),
+                    location: message.location().clone(),
+                    index,
+                    annotation: match annotation {
+                        Annotation::Enum { name, .. } => format!("enum({name})"),
+                        Annotation::Symbol { .. } => "sym".to_string(),
+                    },
+                    argument: argument.type_name(),
+                });
+            }
+            if let Annotation::Enum { name, ty: None } = annotation {
+                issues.push(LintIssue::UnknownEnum {
+                    id: message.id(),
+                    location: message.location().clone(),
+                    index,
+                    name: name.clone(),
+                });
+            }
+        }
+    }
+
+    // Arguments are attributed to messages by location and format string
+    let duplicates = messages
+        .iter()
+        .into_group_map_by(|x| (x.location(), x.format()))
+        .into_iter()
+        .filter(|(_, x)| x.len() > 1)
+        .sorted_by_key(|(_, x)| x[0].id())
+        .map(|((location, format), x)| LintIssue::DuplicateMessage {
+            ids: x.iter().map(|x| x.id()).collect(),
+            location: location.clone(),
+            format: format.to_string(),
+        });
+    issues.extend(duplicates);
+
+    // Strings are sent by id, which messages print them isn't known. Assume they are printed from
+    // the file declaring them
+    let string_files = messages
+        .iter()
+        .filter(|x| {
+            x.arguments()
+                .iter()
+                .any(|x| matches!(x, ULogArgument::ULogString { .. }))
+        })
+        .map(|x| &x.location().file)
+        .collect::<HashSet<_>>();
+    let unused = system
+        .ulog_strings()
+        .values()
+        .filter(|x| !string_files.contains(&x.location().file))
+        .sorted_by_key(|x| x.id())
+        .map(|x| LintIssue::UnusedString {
+            id: x.id(),
+            location: x.location().clone(),
+            string: x.string().clone(),
+        });
+    issues.extend(unused);
+
+    issues
+}
+
+/// Checks whether a placeholder can format a value of the type of an argument
+fn formats(placeholder: &str, argument: &ULogArgument) -> bool {
+    let Ok(format) = FormatString::from_string(placeholder.to_string()) else {
+        return false;
+    };
+    let argument = argument.with_sample_value();
+    Formatter::from(&format)
+        .push_arg(&argument)
+        .format()
+        .is_ok()
+}
+
+/// Checks whether an argument is an integer, the only values annotations apply to
+fn is_integer(argument: &ULogArgument) -> bool {
+    matches!(
+        argument,
+        ULogArgument::Int8 { .. }
+            | ULogArgument::Int16 { .. }
+            | ULogArgument::Int32 { .. }
+            | ULogArgument::Int64 { .. }
+            | ULogArgument::UInt8 { .. }
+            | ULogArgument::UInt16 { .. }
+            | ULogArgument::UInt32 { .. }
+            | ULogArgument::UInt64 { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lint::{LintIssue, lint_system};
+    use crate::severity::SeverityLevel;
+    use crate::test_support::{SystemBuilder, location};
+    use std::sync::Arc;
+
+    #[test]
+    fn issues() {
+        let main = location("main.c", 20);
+        let other = location("uart.c", 3);
+        let system = SystemBuilder::new(0x0102)
+            .message_at(
+                0,
+                "{:x} {:>4}",
+                &[2, 249],
+                main.clone(),
+                SeverityLevel::Info,
+            )
+            .message_at(1, "{}", &[], main.clone(), SeverityLevel::Info)
+            .message_at(2, "{}", &[6], main.clone(), SeverityLevel::Info)
+            .message_at(
+                3,
+                "{:sym} {:enum(state_t)}",
+                &[2, 248],
+                main.clone(),
+                SeverityLevel::Info,
+            )
+            .string(0, "idle", main.clone())
+            .string(1, "rx", other.clone())
+            .build();
+
+        assert_eq!(
+            lint_system(&system),
+            vec![
+                LintIssue::IncompatibleSpec {
+                    id: 0,
+                    location: main.clone(),
+                    index: 0,
+                    placeholder: "{:x}".to_string(),
+                    argument: "float".to_string(),
+                },
+                LintIssue::ArgumentCount {
+                    id: 1,
+                    location: main.clone(),
+                    placeholders: 1,
+                    arguments: 0,
+                },
+                LintIssue::IncompatibleAnnotation {
+                    id: 3,
+                    location: main.clone(),
+                    index: 0,
+                    annotation: "sym".to_string(),
+                    argument: "float".to_string(),
+                },
+                LintIssue::UnknownEnum {
+                    id: 3,
+                    location: main.clone(),
+                    index: 1,
+                    name: "state_t".to_string(),
+                },
+                LintIssue::DuplicateMessage {
+                    ids: vec![1, 2],
+                    location: main,
+                    format: "{}".to_string(),
+                },
+                LintIssue::UnusedString {
+                    id: 1,
+                    location: other,
+                    string: Arc::new("rx".to_string()),
+                },
+            ]
+        );
+        let warnings = lint_system(&system)
+            .iter()
+            .filter(|x| x.is_warning())
+            .count();
+        assert_eq!(warnings, 1);
+    }
+}
@@ -1,7 +1,8 @@
+use serde::Serialize;
 use std::sync::Arc;
 
 /// Simple struct that represents a location in a file
-#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[derive(Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Serialize)]
 pub struct Location {
     pub file: Arc<String>,
     pub line: usize,
@@ -1,29 +1,38 @@
-pub mod elf;
-pub mod location;
-pub mod severity;
-mod splitter;
-pub mod ulog_argument;
-pub mod ulog_message;
-pub mod ulog_string;
-pub mod ulog_system_info;
-mod util;
-
-use crate::elf::{ElfParseError, attempt_load_elf};
-use crate::ulog_argument::ULogArgumentReadError;
-use crate::ulog_message::ULogMessageFormatError;
-use crate::ulog_system_info::ULogSystemInfo;
-use crate::util::hexdump;
-use byteorder::{BE, ReadBytesExt};
 use clap::ValueHint;
-use clap::{Args, Parser};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
 use color_backtrace::BacktracePrinter;
+use humantime::format_rfc3339_millis;
+use owo_colors::OwoColorize;
+use serde::Serialize;
 use serialport::{SerialPortInfo, SerialPortType};
 use snafu::{Backtrace, ErrorCompat, OptionExt, Report, ResultExt, Snafu};
-use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader, stdin};
-use std::path::PathBuf;
+use std::io::{BufReader, BufWriter, Read, Write, stdin, stdout};
+use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use ulog_decoder::catalog::{Catalog, CatalogFilter, CatalogMessage, CatalogString};
+use ulog_decoder::decoded_entry::DecodedEntry;
+use ulog_decoder::decoder::{DecodeError, DecodeStatistics, Decoder, SystemLoadError, load_system};
+use ulog_decoder::dictionary::{DictionaryError, write_dictionary};
+use ulog_decoder::endianness::Endianness;
+use ulog_decoder::follow_reader::FollowReader;
+use ulog_decoder::frame_header::DeviceTimestamp;
+use ulog_decoder::frame_reader::{FrameReader, RawFrame};
+use ulog_decoder::framing::Framing;
+use ulog_decoder::lint::{LintIssue, lint_system};
+use ulog_decoder::map_diff::{MapDiff, MessageChange, MetaChange};
+use ulog_decoder::serial_source::{COMMON_BAUDRATES, SerialMatcher, SerialSource, detect_baudrate};
+use ulog_decoder::severity::SeverityLevel;
+use ulog_decoder::source::SourceEvent;
+use ulog_decoder::tcp_source::{TcpClientSource, TcpServerSource};
+use ulog_decoder::timestamp::ReceiveTimestamp;
+use ulog_decoder::udp_source::UdpSource;
+use ulog_decoder::ulog_system_info::ULogSystemInfo;
+use ulog_decoder::util::hexdump;
 
 #[derive(Snafu, Debug)]
 pub enum ULogDecoderError {
@@ -39,54 +48,44 @@ pub enum ULogDecoderError {
         source: serialport::Error,
         port: String,
     },
-    #[snafu(display("Failed to find a serial source port"))]
-    NoSerialSource { backtrace: Backtrace },
-    #[snafu(display("Failed to load ELF file ({file})"))]
-    ELFLoad {
-        file: String,
-        #[snafu(backtrace)]
-        #[snafu(source(from(ElfParseError, Box::new)))]
-        source: Box<ElfParseError>,
-    },
-    #[snafu(display("Failed to read entry"))]
-    EntryRead {
+    #[snafu(display("Failed to listen for TCP clients ({address})"))]
+    TcpListen {
         backtrace: Backtrace,
         source: std::io::Error,
+        address: String,
     },
-    #[snafu(display("Failed to decode rzcobs frame"))]
-    Rzcobs { backtrace: Backtrace },
-    #[snafu(display("Failed to read system id"))]
-    SystemIdRead {
+    #[snafu(display("Failed to bind UDP socket ({address})"))]
+    UdpBind {
         backtrace: Backtrace,
         source: std::io::Error,
+        address: String,
     },
-    #[snafu(display("Failed to read message id"))]
-    MessageIdRead {
+    #[snafu(display("Failed to detect the baud rate of {port}, no rate received a valid frame"))]
+    NoBaudrate { backtrace: Backtrace, port: String },
+    #[snafu(display("Failed to find a serial source port"))]
+    NoSerialSource { backtrace: Backtrace },
+    #[snafu(display("Failed to set the Ctrl-C handler"))]
+    CtrlCHandler {
         backtrace: Backtrace,
-        source: std::io::Error,
+        source: ctrlc::Error,
     },
-    #[snafu(display("Message not found!"))]
-    UnknownMessage { backtrace: Backtrace },
-    #[snafu(display("System not found!"))]
-    UnknownSystem { backtrace: Backtrace },
-    #[snafu(display("Failed to decode arguments"))]
-    ArgumentDecode {
-        #[snafu(backtrace)]
-        source: ULogArgumentReadError,
-    },
-    #[snafu(display("Failed to format message"))]
-    Format {
+    #[snafu(display("Failed to load map file ({file})"))]
+    MapLoad {
+        file: String,
         #[snafu(backtrace)]
-        source: ULogMessageFormatError,
+        source: SystemLoadError,
     },
-    #[snafu(display(
-        "Failed to load {file} because the system id {system_id:x} is already in use"
-    ))]
-    DuplicateSystemId {
+    #[snafu(display("Failed to create output file ({file})"))]
+    OutputCreate {
         backtrace: Backtrace,
-        system_id: u16,
+        source: std::io::Error,
         file: String,
     },
+    #[snafu(display("Failed to export map"))]
+    MapExport {
+        #[snafu(backtrace)]
+        source: DictionaryError,
+    },
 }
 
 /// Prints the backtrace assosicated with an error, if there is one
@@ -101,24 +100,359 @@ fn print_backtrace(err: &impl ErrorCompat) {
 }
 
 #[derive(Parser, Debug)]
-#[command(about, long_about = None, version, version, author)]
+#[command(
+    about,
+    long_about = None,
+    version,
+    version,
+    author,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct CliArgs {
-    /// Path to ELF file containing a uLog map
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Path to ELF file or dictionary containing a uLog map
     #[arg(required = true)]
     map_files: Vec<String>,
     #[command(flatten)]
     source: SourceArgs,
-    /// Baud rate to use when opening a serial port
+    /// Keep reading the source file as it grows, reopening it if it gets truncated or replaced
+    #[arg(long, requires = "from_file", help_heading = "File Source")]
+    follow: bool,
+    /// Baud rate to use when opening a serial port, or auto to try common rates and keep the one receiving the most valid frames
     #[arg(
         short = 'b',
         long,
-        default_value_t = 38400,
+        default_value = "38400",
         help_heading = "Serial Source"
     )]
-    baudrate: u32,
+    baudrate: Baudrate,
+    /// Time spent listening at each rate when the baud rate is auto, such as 500ms or 2s
+    #[arg(
+        long,
+        default_value = "1s",
+        value_parser = humantime::parse_duration,
+        help_heading = "Serial Source",
+        value_name = "DURATION"
+    )]
+    baudrate_window: Duration,
+    /// Select the first USB serial port matching <CRITERIA> when <PORT> is auto. Criteria are comma separated <KEY>=<VALUE> pairs with vid, pid, serial, manufacturer or product as key, such as vid=0483,pid=5740,serial=ABC*. Vid and pid are hexadecimal, other values are case insensitive globs
+    #[arg(
+        long,
+        requires = "from_serial",
+        help_heading = "Serial Source",
+        value_name = "CRITERIA"
+    )]
+    serial_match: Option<SerialMatcher>,
+    /// Number of data bits per character
+    #[arg(long, value_enum, default_value_t = DataBits::Eight, help_heading = "Serial Source")]
+    data_bits: DataBits,
+    /// Parity checking mode
+    #[arg(long, value_enum, default_value_t = Parity::None, help_heading = "Serial Source")]
+    parity: Parity,
+    /// Number of stop bits
+    #[arg(long, value_enum, default_value_t = StopBits::One, help_heading = "Serial Source")]
+    stop_bits: StopBits,
+    /// Flow control mode
+    #[arg(long, value_enum, default_value_t = FlowControl::None, help_heading = "Serial Source")]
+    flow_control: FlowControl,
+    /// DTR state set when opening the port
+    #[arg(long, value_enum, default_value_t = LineState::On, help_heading = "Serial Source")]
+    dtr: LineState,
+    /// RTS state set when opening the port. Left as set by the driver when unspecified
+    #[arg(long, value_enum, help_heading = "Serial Source")]
+    rts: Option<LineState>,
     /// List detected serial ports and exit
     #[arg(short = 'l', long, exclusive = true, help_heading = "Serial Source")]
     list_ports: bool,
+    /// How frames are delimited and encoded in the stream
+    #[arg(long, value_enum, default_value_t = FramingArg::Rzcobs)]
+    framing: FramingArg,
+    /// Wire endianness of the systems that don't declare one in their meta section
+    #[arg(long, value_enum, default_value_t = EndiannessArg::Big)]
+    wire_endianness: EndiannessArg,
+    /// Refuse to load maps with issues the lint subcommand reports, warnings aside
+    #[arg(long)]
+    strict: bool,
+    /// Expect plain text console output between frames, such as bootloader messages, and print each line inline as soon as it arrives instead of failing to decode it. Needs a framing with a delimiter
+    #[arg(long, conflicts_with = "from_udp")]
+    mixed: bool,
+    /// Format used to print decoded entries
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text, help_heading = "Output")]
+    output: OutputFormat,
+    /// Host receive timestamp printed in front of text entries
+    #[arg(short = 't', long, value_enum, default_value_t = TimestampMode::None, help_heading = "Output")]
+    timestamps: TimestampMode,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Export the uLog map of an ELF file to a standalone JSON dictionary, which can be given instead of the ELF file to decode its entries
+    ExportMap(ExportMapArgs),
+    /// List the messages and strings of the uLog map of ELF files or dictionaries
+    Inspect(InspectArgs),
+    /// Check the uLog map of ELF files or dictionaries for messages that would fail to decode or print wrongly, exits with status 1 when issues other than warnings are found
+    Lint(LintArgs),
+    /// Compare the settings, messages and strings of two uLog maps, such as the ones of two firmware versions. Exits with status 1 when entries of the old map would decode differently with the new one
+    Diff(DiffArgs),
+}
+
+#[derive(Args, Debug)]
+struct ExportMapArgs {
+    /// Path to the ELF file to export the uLog map of
+    map_file: String,
+    /// Write the dictionary to <FILE> instead of standard output
+    #[arg(short = 'o', long, value_name = "FILE")]
+    output: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct InspectArgs {
+    /// Path to ELF file or dictionary containing a uLog map
+    #[arg(required = true)]
+    map_files: Vec<String>,
+    /// Only list messages with one of these severity levels, such as error,warning
+    #[arg(long, value_delimiter = ',', value_name = "LEVELS")]
+    severity: Vec<SeverityLevel>,
+    /// Only list messages and strings declared in a file whose path matches <GLOB>, such as *uart.c. Matching is case insensitive
+    #[arg(long, value_name = "GLOB")]
+    file: Option<String>,
+    /// Format used to print the listing
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct LintArgs {
+    /// Path to ELF file or dictionary containing a uLog map
+    #[arg(required = true)]
+    map_files: Vec<String>,
+    /// Format used to print the issues
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// Path to the ELF file or dictionary of the old map
+    old: String,
+    /// Path to the ELF file or dictionary of the new map
+    new: String,
+    /// Format used to print the differences
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Baudrate {
+    /// Detected from the received data
+    Auto,
+    Fixed(u32),
+}
+
+impl FromStr for Baudrate {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Baudrate::Auto),
+            s => s.parse().map(Baudrate::Fixed),
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+enum DataBits {
+    #[value(name = "5")]
+    Five,
+    #[value(name = "6")]
+    Six,
+    #[value(name = "7")]
+    Seven,
+    #[value(name = "8")]
+    Eight,
+}
+
+impl From<DataBits> for serialport::DataBits {
+    fn from(value: DataBits) -> Self {
+        match value {
+            DataBits::Five => serialport::DataBits::Five,
+            DataBits::Six => serialport::DataBits::Six,
+            DataBits::Seven => serialport::DataBits::Seven,
+            DataBits::Eight => serialport::DataBits::Eight,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+enum Parity {
+    None,
+    Odd,
+    Even,
+}
+
+impl From<Parity> for serialport::Parity {
+    fn from(value: Parity) -> Self {
+        match value {
+            Parity::None => serialport::Parity::None,
+            Parity::Odd => serialport::Parity::Odd,
+            Parity::Even => serialport::Parity::Even,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+enum StopBits {
+    #[value(name = "1")]
+    One,
+    #[value(name = "2")]
+    Two,
+}
+
+impl From<StopBits> for serialport::StopBits {
+    fn from(value: StopBits) -> Self {
+        match value {
+            StopBits::One => serialport::StopBits::One,
+            StopBits::Two => serialport::StopBits::Two,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+enum FlowControl {
+    /// No flow control
+    None,
+    /// XON/XOFF
+    Software,
+    /// RTS/CTS
+    Hardware,
+}
+
+impl From<FlowControl> for serialport::FlowControl {
+    fn from(value: FlowControl) -> Self {
+        match value {
+            FlowControl::None => serialport::FlowControl::None,
+            FlowControl::Software => serialport::FlowControl::Software,
+            FlowControl::Hardware => serialport::FlowControl::Hardware,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+enum LineState {
+    /// Asserted
+    On,
+    /// Cleared
+    Off,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+enum FramingArg {
+    /// Reverse zero compressing COBS, delimited with a null byte
+    Rzcobs,
+    /// Plain COBS, delimited with a null byte
+    Cobs,
+    /// SLIP, delimited with an END byte
+    Slip,
+    /// Big endian u16 length followed by the payload
+    LengthPrefixed,
+    /// Payload only, every read of the source holds exactly one frame
+    Unframed,
+}
+
+impl From<FramingArg> for Framing {
+    fn from(value: FramingArg) -> Self {
+        match value {
+            FramingArg::Rzcobs => Framing::Rzcobs,
+            FramingArg::Cobs => Framing::Cobs,
+            FramingArg::Slip => Framing::Slip,
+            FramingArg::LengthPrefixed => Framing::LengthPrefixed,
+            FramingArg::Unframed => Framing::Unframed,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+enum EndiannessArg {
+    Big,
+    Little,
+}
+
+impl From<EndiannessArg> for Endianness {
+    fn from(value: EndiannessArg) -> Self {
+        match value {
+            EndiannessArg::Big => Endianness::Big,
+            EndiannessArg::Little => Endianness::Little,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+enum OutputFormat {
+    /// Human readable colored text
+    Text,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Eq, PartialEq)]
+enum TimestampMode {
+    /// No timestamp
+    None,
+    /// Wall clock time
+    Absolute,
+    /// Time since the start of the stream
+    Relative,
+    /// Time since the previous entry
+    Delta,
+}
+
+/// Record printed for every issue of the JSON lint output
+#[derive(Serialize)]
+struct JsonLintIssue<'a> {
+    /// Path of the map the issue was found in
+    map: &'a str,
+    system_id: u16,
+    /// Whether the issue is only a warning, see [`LintIssue::is_warning`]
+    warning: bool,
+    #[serde(flatten)]
+    issue: &'a LintIssue,
+}
+
+/// Record printed for every line of the JSON output
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonRecord<'a> {
+    Entry(&'a DecodedEntry),
+    Error {
+        /// Error report
+        error: String,
+        /// Decoded frame, in hex
+        data: Option<String>,
+        /// Frame as it was received, in hex
+        raw: Option<String>,
+        /// Host time at which the frame was received
+        received: Option<ReceiveTimestamp>,
+        /// Tag of the stream the frame was read from
+        source: Option<&'a str>,
+        /// Entries lost right before the frame, according to the sequence counters
+        lost: u32,
+    },
+    /// Counters printed at the end of the session
+    Statistics(DecodeStatistics),
+    /// Plain console text line received between frames
+    Text {
+        text: &'a str,
+        received: ReceiveTimestamp,
+        source: Option<&'a str>,
+    },
+    Connected {
+        source: &'a str,
+    },
+    Disconnected {
+        source: &'a str,
+    },
 }
 
 #[derive(Args, Debug)]
@@ -140,6 +474,15 @@ struct SourceArgs {
         default_missing_value = "auto"
     )]
     from_serial: Option<String>,
+    /// Connect to the TCP server at <ADDRESS> and use it as the uLog stream source, reconnecting whenever the connection is lost
+    #[arg(long, help_heading = "TCP Source", value_name = "ADDRESS")]
+    from_tcp: Option<String>,
+    /// Listen on <ADDRESS> for TCP clients and use each of them as a uLog stream source
+    #[arg(long, help_heading = "TCP Source", value_name = "ADDRESS")]
+    listen_tcp: Option<String>,
+    /// Bind a UDP socket to <ADDRESS> and use the datagrams it receives as the uLog stream source, each datagram holding one or more frames
+    #[arg(long, help_heading = "UDP Source", value_name = "ADDRESS")]
+    from_udp: Option<String>,
 }
 
 /// Wrapper around main_inner() with error handling for fatal errors
@@ -147,9 +490,198 @@ fn main() {
     if let Err(err) = &main_inner() {
         eprintln!("An error occurred: {}", Report::from_error(err));
         print_backtrace(&err);
+        std::process::exit(1);
     }
 }
 
+/// Encodes bytes as a lowercase hex string
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|x| format!("{x:02x}")).collect()
+}
+
+/// Prints decoded entries and decoding errors in the selected output format
+#[derive(Clone)]
+struct Printer {
+    format: OutputFormat,
+    timestamps: TimestampMode,
+    /// Receive time of the previous entry, for delta timestamps
+    previous: Option<Duration>,
+}
+
+impl Printer {
+    /// Prints an informational banner, kept out of stdout for machine readable output
+    fn banner(&self, text: &str) {
+        match self.format {
+            OutputFormat::Text => println!("{text}\n\n"),
+            OutputFormat::Json => eprintln!("{text}"),
+        }
+    }
+
+    /// Formats the receive time of an entry according to the timestamp mode, including a
+    /// trailing space when not empty
+    fn timestamp(&mut self, received: Option<ReceiveTimestamp>) -> String {
+        let Some(received) = received else {
+            return String::new();
+        };
+        let previous = self.previous.replace(received.monotonic);
+
+        match self.timestamps {
+            TimestampMode::None => String::new(),
+            TimestampMode::Absolute => format!("{} ", format_rfc3339_millis(received.wall)),
+            TimestampMode::Relative => format!("{:>12.6} ", received.monotonic.as_secs_f64()),
+            TimestampMode::Delta => format!(
+                "+{:.6} ",
+                received
+                    .monotonic
+                    .saturating_sub(previous.unwrap_or(received.monotonic))
+                    .as_secs_f64()
+            ),
+        }
+    }
+
+    fn entry(&mut self, entry: &DecodedEntry) {
+        if self.format == OutputFormat::Text && entry.lost > 0 {
+            println!("{:━^80}", format!(" {} ENTRIES LOST ", entry.lost));
+        }
+        match self.format {
+            OutputFormat::Text => println!(
+                "{}{}[{:#}] {}\n    From: 0x{:X?}(file://{}:{}){}",
+                self.timestamp(entry.received),
+                device_time(entry.device_time),
+                entry.severity_level,
+                entry.text,
+                entry.system_id,
+                entry.location.file,
+                entry.location.line,
+                entry
+                    .source
+                    .as_ref()
+                    .map(|x| format!(" via {x}"))
+                    .unwrap_or_default()
+            ),
+            OutputFormat::Json => print_json(&JsonRecord::Entry(entry)),
+        }
+    }
+
+    /// Prints the statistics of the session
+    fn statistics(&self, statistics: DecodeStatistics) {
+        match self.format {
+            OutputFormat::Text => eprintln!(
+                "{} entries decoded, {} corrupted frames, {} frames failed to decode, {} entries lost",
+                statistics.decoded, statistics.corrupted, statistics.failed, statistics.lost
+            ),
+            OutputFormat::Json => print_json(&JsonRecord::Statistics(statistics)),
+        }
+    }
+
+    /// Prints a plain console text line
+    fn text(&mut self, text: &str, received: ReceiveTimestamp, source: Option<&str>) {
+        match self.format {
+            OutputFormat::Text => println!(
+                "{}[{}] {text}{}",
+                self.timestamp(Some(received)),
+                "TEXT".dimmed(),
+                source.map(|x| format!(" via {x}")).unwrap_or_default()
+            ),
+            OutputFormat::Json => print_json(&JsonRecord::Text {
+                text,
+                received,
+                source,
+            }),
+        }
+    }
+
+    /// Prints an error that occurred while decoding `frame`
+    fn decode_error(&self, err: &DecodeError, frame: &RawFrame) {
+        if self.format == OutputFormat::Text && err.lost() > 0 {
+            println!("{:━^80}", format!(" {} ENTRIES LOST ", err.lost()));
+        }
+        match self.format {
+            // A corrupted frame says nothing about the maps, no need for the details
+            OutputFormat::Text if err.is_corruption() => eprintln!(
+                "Dropped a corrupted frame: {}{}",
+                Report::from_error(err).to_string().trim_end(),
+                frame
+                    .source
+                    .as_ref()
+                    .map(|x| format!(" via {x}"))
+                    .unwrap_or_default()
+            ),
+            OutputFormat::Text => {
+                eprintln!(
+                    "An error occurred processing a log entry: {}",
+                    Report::from_error(err)
+                );
+                // If we have the parsed message, print whatever we managed to parse so far
+                eprintln!("{:━^80}\n{:#?}", " PARSED ENTRY ", err.message());
+                // And the decoded message
+                eprintln!(
+                    "{:━^80}\n{}",
+                    " DECODED ENTRY ",
+                    err.data().map(hexdump).unwrap_or("None".to_string())
+                );
+                // Then the raw entry in the event all else fails
+                eprintln!("{:━^80}\n{}", " RAW ENTRY ", hexdump(&frame.data));
+                print_backtrace(err);
+            }
+            OutputFormat::Json => print_json(&JsonRecord::Error {
+                error: Report::from_error(err).to_string().trim_end().to_string(),
+                data: err.data().map(to_hex),
+                raw: Some(to_hex(&frame.data)),
+                received: Some(frame.timestamp),
+                source: frame.source.as_deref(),
+                lost: err.lost(),
+            }),
+        }
+    }
+
+    /// Prints an error that occurred while reading from the source
+    fn source_error(&self, err: &std::io::Error) {
+        match self.format {
+            OutputFormat::Text => eprintln!("An error occurred reading from the source: {err}"),
+            OutputFormat::Json => print_json(&JsonRecord::Error {
+                error: err.to_string(),
+                data: None,
+                raw: None,
+                received: None,
+                source: None,
+                lost: 0,
+            }),
+        }
+    }
+
+    /// Prints a marker when a peer connects or disconnects
+    fn connection(&self, peer: &str, connected: bool) {
+        match (self.format, connected) {
+            (OutputFormat::Text, true) => println!("{:━^80}", format!(" CONNECTED {peer} ")),
+            (OutputFormat::Text, false) => println!("{:━^80}", format!(" DISCONNECTED {peer} ")),
+            (OutputFormat::Json, true) => print_json(&JsonRecord::Connected { source: peer }),
+            (OutputFormat::Json, false) => print_json(&JsonRecord::Disconnected { source: peer }),
+        }
+    }
+}
+
+/// Formats the device time of an entry, in seconds if the tick rate is known or in ticks otherwise,
+/// including a trailing space when not empty
+fn device_time(device_time: Option<DeviceTimestamp>) -> String {
+    match device_time {
+        None => String::new(),
+        Some(DeviceTimestamp {
+            seconds: Some(seconds),
+            ..
+        }) => format!("@{seconds:.6}s "),
+        Some(DeviceTimestamp { ticks, .. }) => format!("@{ticks} "),
+    }
+}
+
+/// Prints a single JSON record on its own line
+fn print_json(record: &JsonRecord) {
+    println!(
+        "{}",
+        serde_json::to_string(record).expect("Failed to serialize record")
+    );
+}
+
 /// Gets a sorted list of all detected serial ports
 fn get_serial_ports() -> Vec<SerialPortInfo> {
     let mut ports = serialport::available_ports().unwrap_or_default();
@@ -161,176 +693,443 @@ fn get_serial_ports() -> Vec<SerialPortInfo> {
     ports
 }
 
-fn main_inner() -> Result<(), ULogDecoderError> {
-    let args = CliArgs::parse();
-
-    // Special mode: list serial ports and exit
-    if args.list_ports {
-        for p in get_serial_ports() {
-            println!("- {}", p.port_name);
-        }
-        return Ok(());
+/// Prints the problems found while loading a map that didn't prevent it from loading
+fn print_warnings(map_file: &str, system: &ULogSystemInfo) {
+    for warning in system.warnings() {
+        eprintln!("Warning ({map_file}): {warning}");
     }
+}
 
-    // Squash down all possible readers into a Box<dyn BufRead>
-    let mut reader: Box<dyn BufRead> = match (
-        args.source.from_file,
-        args.source.from_stdin,
-        args.source.from_serial,
-    ) {
-        // Source: File
-        (Some(file), _, _) => {
-            println!("Source: file {file}\n\n");
-            Box::new(BufReader::new(
-                File::open(&file).with_context(|_| FileSourceOpenSnafu { file })?,
-            ))
+/// Loads the uLog map of an ELF file or dictionary, printing its warnings
+fn load_map_file(map_file: &str) -> Result<ULogSystemInfo, ULogDecoderError> {
+    let system =
+        load_system(Path::new(map_file)).with_context(|_| MapLoadSnafu { file: map_file })?;
+    print_warnings(map_file, &system);
+    Ok(system)
+}
+
+/// Exports the uLog map of an ELF file to a dictionary
+fn export_map(args: ExportMapArgs) -> Result<(), ULogDecoderError> {
+    let system = load_map_file(&args.map_file)?;
+    let writer: Box<dyn Write> = match args.output {
+        Some(file) => Box::new(BufWriter::new(
+            File::create(&file).with_context(|_| OutputCreateSnafu { file })?,
+        )),
+        None => Box::new(stdout().lock()),
+    };
+    write_dictionary(&system, writer).context(MapExportSnafu)?;
+    Ok(())
+}
+
+/// Lists the messages and strings of the uLog map of every file
+fn inspect(args: InspectArgs) -> Result<(), ULogDecoderError> {
+    let filter = CatalogFilter {
+        severities: args.severity,
+        file: args.file,
+    };
+    for map_file in &args.map_files {
+        let system = load_map_file(map_file)?;
+        let catalog = Catalog::from_system(&system, &filter);
+
+        if args.output == OutputFormat::Json {
+            println!(
+                "{}",
+                serde_json::to_string(&catalog).expect("Failed to serialize catalog")
+            );
+            continue;
         }
-        // Source: serial
-        (_, _, Some(port)) => {
-            let mut port = port;
-            // Replace auto by the first detected serial port
-            if port == "auto" {
-                port = get_serial_ports()
-                    .first()
-                    .ok_or(NoSerialSourceSnafu.build())?
-                    .port_name
-                    .clone()
-            }
-            println!("Source: serial port {port} {}\n\n", args.baudrate);
-            Box::new(BufReader::new(
-                serialport::new(&port, args.baudrate)
-                    .dtr_on_open(true)
-                    // Timeout is important as by default we timeout immediately if reading when theres no data ready
-                    .timeout(Duration::MAX)
-                    .open()
-                    .with_context(|_| SerialSourceOpenSnafu { port })?,
-            ))
+        println!(
+            "System 0x{:04X} ({map_file}), {} messages, {} strings",
+            catalog.system_id,
+            catalog.messages.len(),
+            catalog.strings.len()
+        );
+        for message in &catalog.messages {
+            println!(
+                "  {:>5} [{:#}] {:?} ({})\n        From: {}:{}",
+                message.id,
+                message.severity,
+                message.format,
+                message.arguments.join(", "),
+                message.location.file,
+                message.location.line
+            );
         }
-        // Source: stdin, default
-        (_, true, _) | (None, false, None) => {
-            println!("Source: stdin\n\n");
-            Box::new(stdin().lock())
+        for string in &catalog.strings {
+            println!(
+                "  {:>5} [{}] {:?}\n        From: {}:{}",
+                string.id,
+                "STRING".dimmed(),
+                string.string,
+                string.location.file,
+                string.location.line
+            );
         }
-    };
+    }
+    Ok(())
+}
 
-    // Load all files into a hashmap with the system id
-    let mut systems = HashMap::<u16, ULogSystemInfo>::new();
+/// Prints the lint issues of the uLog map of every file, exits with status 1 if there are any
+/// besides warnings
+fn lint(args: LintArgs) -> Result<(), ULogDecoderError> {
+    let mut count = 0;
+    let mut warnings = 0;
     for map_file in &args.map_files {
-        let system = attempt_load_elf(&PathBuf::from(map_file))
-            .with_context(|_| ELFLoadSnafu { file: map_file })?;
-
-        // duplicate is Some when there already is an entry in the map, we want to error out if this is the case
-        let duplicate = systems.insert(system.system_id(), system);
-        if let Some(duplicate) = duplicate {
-            return DuplicateSystemIdSnafu {
-                system_id: duplicate.system_id(),
-                file: map_file,
+        let system = load_map_file(map_file)?;
+        for issue in &lint_system(&system) {
+            let warning = issue.is_warning();
+            match warning {
+                true => warnings += 1,
+                false => count += 1,
+            }
+            match args.output {
+                OutputFormat::Text if warning => {
+                    println!("{map_file}: {} {issue}", "warning:".yellow())
+                }
+                OutputFormat::Text => println!("{map_file}: {issue}"),
+                OutputFormat::Json => println!(
+                    "{}",
+                    serde_json::to_string(&JsonLintIssue {
+                        map: map_file,
+                        system_id: system.system_id(),
+                        warning,
+                        issue,
+                    })
+                    .expect("Failed to serialize issue")
+                ),
             }
-            .fail();
         }
     }
 
-    // main message handling loop
-    let mut buf = vec![];
-    loop {
-        // Hoist data and message to print it out if theres an error
-        let mut data = None;
-        let mut message = None;
-
-        // Handler is in a closure to not die if a single message fails to parse
-        // Returns true when EOF is reached
-        let result = (|| -> Result<bool, ULogDecoderError> {
-            // Clear out the previous message
-            buf.clear();
-
-            // All rzcobs frames are delimited with a null byte
-            loop {
-                let result = reader.read_until(0x00, &mut buf);
-                if result
-                    .as_ref()
-                    .is_err_and(|err| err.kind() == std::io::ErrorKind::TimedOut)
-                {
-                    // We ignore timeout errors and silently try again
-                    continue;
-                }
+    eprintln!("{count} issues and {warnings} warnings found");
+    if count > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
 
-                // We have our data, stop reading
-                if result.is_ok() {
-                    break;
-                }
+/// Prints a string of a map, prefixed with a marker telling what happened to it
+fn print_catalog_string(marker: &str, string: &CatalogString) {
+    println!(
+        "{marker} {:>5} {:?}\n        From: {}:{}",
+        string.id, string.string, string.location.file, string.location.line
+    );
+}
 
-                //A non timeout error happened, propagate it
-                result.context(EntryReadSnafu)?;
-            }
+/// Prints a message of a map, prefixed with a marker telling what happened to it
+fn print_catalog_message(marker: &str, message: &CatalogMessage) {
+    println!(
+        "{marker} {:>5} [{:#}] {:?} ({})\n        From: {}:{}",
+        message.id,
+        message.severity,
+        message.format,
+        message.arguments.join(", "),
+        message.location.file,
+        message.location.line
+    );
+}
 
-            // Handle EOFFf by early returning with true to request exit
-            if buf.is_empty() {
-                return Ok(true);
+/// Prints the differences between two maps, exits with status 1 if they break the decoding of
+/// entries of the old map
+fn diff(args: DiffArgs) -> Result<(), ULogDecoderError> {
+    let old = load_map_file(&args.old)?;
+    let new = load_map_file(&args.new)?;
+    let diff = MapDiff::new(&old, &new);
+
+    match args.output {
+        OutputFormat::Text => {
+            if diff.old_system_id != diff.new_system_id {
+                println!(
+                    "System id changed from 0x{:04X} to 0x{:04X}",
+                    diff.old_system_id, diff.new_system_id
+                );
+            }
+            let option = |x: Option<String>| x.unwrap_or_else(|| "none".to_string());
+            for change in &diff.meta {
+                match change {
+                    MetaChange::HeaderVersion { old, new } => {
+                        println!("Header version changed from {old} to {new}")
+                    }
+                    MetaChange::TickRate { old, new } => println!(
+                        "Tick rate changed from {} to {}",
+                        option(old.map(|x| format!("{x} Hz"))),
+                        option(new.map(|x| format!("{x} Hz")))
+                    ),
+                    MetaChange::Crc { old, new } => println!(
+                        "CRC changed from {} to {}",
+                        option(old.map(|x| format!("{x} bits"))),
+                        option(new.map(|x| format!("{x} bits")))
+                    ),
+                    MetaChange::Endianness { old, new } => println!(
+                        "Endianness changed from {} to {}",
+                        option(old.map(|x| x.to_string())),
+                        option(new.map(|x| x.to_string()))
+                    ),
+                }
+            }
+            for message in &diff.removed {
+                print_catalog_message(&"-".red().to_string(), message);
+            }
+            for message in &diff.added {
+                print_catalog_message(&"+".green().to_string(), message);
+            }
+            for message in &diff.modified {
+                println!("{} {:>5}", "~".yellow(), message.id);
+                for change in &message.changes {
+                    match change {
+                        MessageChange::Format { old, new } => {
+                            println!("        Format: {old:?} -> {new:?}")
+                        }
+                        MessageChange::Severity { old, new } => {
+                            println!("        Severity: {old:#} -> {new:#}")
+                        }
+                        MessageChange::Arguments { old, new } => println!(
+                            "        Arguments: ({}) -> ({})",
+                            old.join(", "),
+                            new.join(", ")
+                        ),
+                        MessageChange::Location { old, new } => println!(
+                            "        From: {}:{} -> {}:{}",
+                            old.file, old.line, new.file, new.line
+                        ),
+                    }
+                }
             }
+            for string in &diff.removed_strings {
+                print_catalog_string(&"-".red().to_string(), string);
+            }
+            for string in &diff.added_strings {
+                print_catalog_string(&"+".green().to_string(), string);
+            }
+            for string in &diff.modified_strings {
+                println!(
+                    "{} {:>5} {:?} -> {:?}",
+                    "~".yellow(),
+                    string.id,
+                    string.old,
+                    string.new
+                );
+            }
+            eprintln!(
+                "{} messages added, {} removed, {} modified",
+                diff.added.len(),
+                diff.removed.len(),
+                diff.modified.len()
+            );
+            eprintln!(
+                "{} strings added, {} removed, {} modified",
+                diff.added_strings.len(),
+                diff.removed_strings.len(),
+                diff.modified_strings.len()
+            );
+        }
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string(&diff).expect("Failed to serialize diff")
+        ),
+    }
 
-            // Rzcobs decode the message
-            data = Some(rzcobs::decode(&buf[0..(buf.len() - 1)]).map_err(|_| RzcobsSnafu.build())?);
-            // Funky ref taking because the read_* functions want a mut ref to a ref which is awkward
-            let data = &mut (&data.as_ref().unwrap()[..]);
+    if diff.is_breaking() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
 
-            // Get the system and message id
-            let system_id = data.read_u16::<BE>().context(SystemIdReadSnafu)?;
-            let message_id = data.read_u16::<BE>().context(MessageIdReadSnafu)?;
+fn main_inner() -> Result<(), ULogDecoderError> {
+    let args = CliArgs::parse();
 
-            // Find the system from the system map
-            let system = systems.get(&system_id).context(UnknownSystemSnafu)?;
+    match args.command {
+        Some(Command::ExportMap(args)) => return export_map(args),
+        Some(Command::Inspect(args)) => return inspect(args),
+        Some(Command::Lint(args)) => return lint(args),
+        Some(Command::Diff(args)) => return diff(args),
+        None => {}
+    }
 
-            // Get the message template from the system's message map
-            message = Some(
-                system
-                    .messages()
-                    .get(&message_id)
-                    .context(UnknownMessageSnafu)?,
-            );
-            // Unwrap is safe here because we just now set it to Some
-            let message = message.as_mut().unwrap();
+    // Special mode: list serial ports and exit
+    if args.list_ports {
+        for p in get_serial_ports() {
+            match p.port_type {
+                SerialPortType::UsbPort(usb) => println!(
+                    "- {} vid={:04x},pid={:04x},serial={},manufacturer={},product={}",
+                    p.port_name,
+                    usb.vid,
+                    usb.pid,
+                    usb.serial_number.unwrap_or_default(),
+                    usb.manufacturer.unwrap_or_default(),
+                    usb.product.unwrap_or_default()
+                ),
+                _ => println!("- {}", p.port_name),
+            }
+        }
+        return Ok(());
+    }
 
-            // Let the message read in its arguments
-            let formatted_message = message
-                .formatted_string(data, system.ulog_strings())
-                .context(FormatSnafu)?;
+    let mut printer = Printer {
+        format: args.output,
+        timestamps: args.timestamps,
+        previous: None,
+    };
 
-            // Format and print the message
-            println!(
-                "[{:#}] {}\n    From: 0x{:X?}(file://{}:{})",
-                message.severity_level(),
-                formatted_message,
-                system_id,
-                message.location().file,
-                message.location().line
-            );
-            Ok(false)
-        })();
+    // Load all files into the decoder
+    let framing = Framing::from(args.framing);
+    if args.mixed && framing.delimiter().is_none() {
+        CliArgs::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!("--mixed needs a framing with a delimiter, {framing} frames have none"),
+            )
+            .exit();
+    }
+    let mut decoder = Decoder::new();
+    decoder.set_framing(framing);
+    decoder.set_default_endianness(args.wire_endianness.into());
+    decoder.set_strict(args.strict);
+    for map_file in &args.map_files {
+        let system = decoder
+            .load_map(&PathBuf::from(map_file))
+            .with_context(|_| MapLoadSnafu { file: map_file })?;
+        print_warnings(map_file, system);
+    }
+    // Frames are only recognized among console text once all the maps are loaded
+    let console_text = args.mixed.then(|| decoder.recognizer());
 
-        // EOF was reached, break
-        if result.as_ref().is_ok_and(|x| *x) {
-            break;
+    // Squash down all possible sources into a Box<dyn Iterator>
+    let source: Box<dyn Iterator<Item = SourceEvent>> = if let Some(file) = args.source.from_file {
+        // Source: File
+        printer.banner(&format!("Source: file {file}"));
+        let reader: Box<dyn Read> = if args.follow {
+            Box::new(FollowReader::open(&file).with_context(|_| FileSourceOpenSnafu { file })?)
+        } else {
+            Box::new(File::open(&file).with_context(|_| FileSourceOpenSnafu { file })?)
+        };
+        Box::new(
+            FrameReader::new(BufReader::new(reader))
+                .with_framing(framing)
+                .with_console_text(console_text)
+                .map(SourceEvent::from),
+        )
+    } else if let Some(port) = args.source.from_serial {
+        // Source: serial
+        let mut port = port;
+        // Replace auto by the first detected serial port meeting the criteria, if any
+        if port == "auto" {
+            port = get_serial_ports()
+                .into_iter()
+                .find(|x| args.serial_match.as_ref().is_none_or(|m| m.matches(x)))
+                .ok_or(NoSerialSourceSnafu.build())?
+                .port_name
         }
+        let builder = serialport::new(&port, 0)
+            .data_bits(args.data_bits.into())
+            .parity(args.parity.into())
+            .stop_bits(args.stop_bits.into())
+            .flow_control(args.flow_control.into())
+            .dtr_on_open(args.dtr == LineState::On)
+            // Timeout is important as by default we timeout immediately if reading when theres no data ready
+            .timeout(Duration::MAX);
+        let baudrate = match args.baudrate {
+            Baudrate::Fixed(baudrate) => baudrate,
+            Baudrate::Auto => {
+                eprintln!("Detecting the baud rate of {port}...");
+                detect_baudrate(
+                    &builder,
+                    &port,
+                    COMMON_BAUDRATES,
+                    args.baudrate_window,
+                    framing,
+                    |x| decoder.recognizes(x),
+                )
+                .with_context(|_| SerialSourceOpenSnafu { port: &port })?
+                .with_context(|| NoBaudrateSnafu { port: &port })?
+            }
+        };
+        let builder = builder.baud_rate(baudrate);
 
-        // Error during message read
-        if let Err(err) = &result {
-            eprintln!(
-                "An error occurred processing a log entry: {}",
-                Report::from_error(err)
-            );
-            // If we have the parsed message, print whatever we managed to parse so far
-            eprintln!("{:━^80}\n{:#?}", " PARSED ENTRY ", &message);
-            // And the rzcobs decoded message
-            eprintln!(
-                "{:━^80}\n{}",
-                " DECODED ENTRY ",
-                data.as_deref().map(hexdump).unwrap_or("None".to_string())
-            );
-            // Then the raw entry in the event all else fails
-            eprintln!("{:━^80}\n{}", " RAW ENTRY ", hexdump(&buf));
-            print_backtrace(&err);
+        let parity = match args.parity {
+            Parity::None => 'N',
+            Parity::Odd => 'O',
+            Parity::Even => 'E',
+        };
+        printer.banner(&format!(
+            "Source: serial port {port} {baudrate} {}{parity}{}",
+            u8::from(serialport::DataBits::from(args.data_bits)),
+            u8::from(serialport::StopBits::from(args.stop_bits))
+        ));
+        Box::new(
+            SerialSource::open(
+                builder,
+                &port,
+                args.rts.map(|x| x == LineState::On),
+                framing,
+                console_text,
+            )
+            .with_context(|_| SerialSourceOpenSnafu { port })?,
+        )
+    } else if let Some(address) = args.source.from_tcp {
+        // Source: TCP client
+        printer.banner(&format!("Source: TCP server {address}"));
+        Box::new(TcpClientSource::new(&address, framing, console_text))
+    } else if let Some(address) = args.source.listen_tcp {
+        // Source: TCP server
+        printer.banner(&format!("Source: TCP clients on {address}"));
+        Box::new(
+            TcpServerSource::bind(&address, framing, console_text)
+                .with_context(|_| TcpListenSnafu { address })?,
+        )
+    } else if let Some(address) = args.source.from_udp {
+        // Source: UDP
+        printer.banner(&format!("Source: UDP datagrams on {address}"));
+        Box::new(UdpSource::bind(&address, framing).with_context(|_| UdpBindSnafu { address })?)
+    } else {
+        // Source: stdin, default
+        printer.banner("Source: stdin");
+        Box::new(
+            FrameReader::new(stdin().lock())
+                .with_framing(framing)
+                .with_console_text(console_text)
+                .map(SourceEvent::from),
+        )
+    };
+
+    // main message handling loop, a single entry failing to decode does not stop the loop
+    // Live sources only end with Ctrl-C, still print the statistics then
+    let statistics = Arc::new(Mutex::new(DecodeStatistics::default()));
+    let handler_printer = printer.clone();
+    let handler_statistics = statistics.clone();
+    ctrlc::set_handler(move || {
+        handler_printer.statistics(*handler_statistics.lock().unwrap());
+        std::process::exit(130);
+    })
+    .context(CtrlCHandlerSnafu)?;
+
+    for event in source {
+        match event {
+            SourceEvent::Frame(frame) if frame.text => {
+                let line = String::from_utf8_lossy(&frame.data);
+                printer.text(
+                    line.trim_end_matches(['\r', '\n']),
+                    frame.timestamp,
+                    frame.source.as_deref(),
+                );
+            }
+            SourceEvent::Frame(frame) => match decoder.decode_raw(&frame) {
+                Ok(entry) => printer.entry(&entry),
+                Err(err) => printer.decode_error(&err, &frame),
+            },
+            SourceEvent::Error(err) => printer.source_error(&err),
+            SourceEvent::Connected(peer) => {
+                // A new connection starts a new stream. Only server sources tag their frames, the
+                // others have a single untagged stream
+                decoder.reset_stream(Some(&peer));
+                decoder.reset_stream(None);
+                printer.connection(&peer, true);
+            }
+            SourceEvent::Disconnected(peer) => printer.connection(&peer, false),
         }
+        *statistics.lock().unwrap() = decoder.statistics();
     }
+    printer.statistics(decoder.statistics());
 
     Ok(())
 }
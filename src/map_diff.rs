@@ -0,0 +1,332 @@
+use crate::catalog::{Catalog, CatalogFilter, CatalogMessage, CatalogString};
+use crate::endianness::Endianness;
+use crate::location::Location;
+use crate::severity::SeverityLevel;
+use crate::ulog_system_info::{ULogSystemInfo, ULogSystemMeta};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Differences between two uLog maps. Messages and strings are matched by id, since the id is
+/// what entries carry on the wire
+#[derive(Debug, Clone, Serialize)]
+pub struct MapDiff {
+    pub old_system_id: u16,
+    pub new_system_id: u16,
+    /// Settings of the meta section that changed
+    pub meta: Vec<MetaChange>,
+    /// Messages only found in the new map
+    pub added: Vec<CatalogMessage>,
+    /// Messages only found in the old map
+    pub removed: Vec<CatalogMessage>,
+    pub modified: Vec<ModifiedMessage>,
+    /// Strings only found in the new map
+    pub added_strings: Vec<CatalogString>,
+    /// Strings only found in the old map
+    pub removed_strings: Vec<CatalogString>,
+    /// Strings whose text changed, moved strings aren't listed
+    pub modified_strings: Vec<ModifiedString>,
+}
+
+/// Setting of the meta section that changed between two maps, see [`ULogSystemMeta`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "field", rename_all = "snake_case")]
+pub enum MetaChange {
+    HeaderVersion {
+        old: u8,
+        new: u8,
+    },
+    TickRate {
+        old: Option<u32>,
+        new: Option<u32>,
+    },
+    /// Width of the CRC trailer in bits
+    Crc {
+        old: Option<u8>,
+        new: Option<u8>,
+    },
+    Endianness {
+        old: Option<Endianness>,
+        new: Option<Endianness>,
+    },
+}
+
+/// String found in both maps with a different text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModifiedString {
+    pub id: u16,
+    pub old: Arc<String>,
+    pub new: Arc<String>,
+}
+
+/// Message found in both maps with different definitions
+#[derive(Debug, Clone, Serialize)]
+pub struct ModifiedMessage {
+    pub id: u16,
+    pub changes: Vec<MessageChange>,
+}
+
+/// Part of a message definition that changed between two maps
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "field", rename_all = "snake_case")]
+pub enum MessageChange {
+    Format {
+        old: String,
+        new: String,
+    },
+    Severity {
+        old: SeverityLevel,
+        new: SeverityLevel,
+    },
+    /// Type names of the arguments
+    Arguments {
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    Location {
+        old: Location,
+        new: Location,
+    },
+}
+
+impl MessageChange {
+    /// Whether entries of the message would decode differently, only the location can move
+    /// without affecting them
+    pub fn is_breaking(&self) -> bool {
+        !matches!(self, MessageChange::Location { .. })
+    }
+}
+
+impl MapDiff {
+    /// Compares the messages of an old and a new map
+    pub fn new(old: &ULogSystemInfo, new: &ULogSystemInfo) -> Self {
+        let old_catalog = Catalog::from_system(old, &CatalogFilter::default());
+        let new_catalog = Catalog::from_system(new, &CatalogFilter::default());
+        let mut old_messages: BTreeMap<_, _> = old_catalog
+            .messages
+            .into_iter()
+            .map(|x| (x.id, x))
+            .collect();
+
+        let mut added = vec![];
+        let mut modified = vec![];
+        for new_message in new_catalog.messages {
+            let id = new_message.id;
+            let Some(old_message) = old_messages.remove(&id) else {
+                added.push(new_message);
+                continue;
+            };
+            let changes = message_changes(old_message, new_message);
+            if !changes.is_empty() {
+                modified.push(ModifiedMessage { id, changes });
+            }
+        }
+
+        let mut old_strings: BTreeMap<_, _> =
+            old_catalog.strings.into_iter().map(|x| (x.id, x)).collect();
+        let mut added_strings = vec![];
+        let mut modified_strings = vec![];
+        for new_string in new_catalog.strings {
+            let id = new_string.id;
+            match old_strings.remove(&id) {
+                None => added_strings.push(new_string),
+                Some(old_string) if old_string.string != new_string.string => {
+                    modified_strings.push(ModifiedString {
+                        id,
+                        old: old_string.string,
+                        new: new_string.string,
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+
+        Self {
+            old_system_id: old.system_id(),
+            new_system_id: new.system_id(),
+            meta: meta_changes(old.meta(), new.meta()),
+            added,
+            removed: old_messages.into_values().collect(),
+            modified,
+            added_strings,
+            removed_strings: old_strings.into_values().collect(),
+            modified_strings,
+        }
+    }
+
+    /// Whether the maps hold the same settings, messages and strings under the same system id
+    pub fn is_empty(&self) -> bool {
+        self.old_system_id == self.new_system_id
+            && self.meta.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.added_strings.is_empty()
+            && self.removed_strings.is_empty()
+            && self.modified_strings.is_empty()
+    }
+
+    /// Whether entries of the old map would fail to decode, or decode differently, with the new
+    /// map. Every meta setting changes how frames are read. Added messages and strings, and moved
+    /// locations, don't break anything
+    pub fn is_breaking(&self) -> bool {
+        self.old_system_id != self.new_system_id
+            || !self.meta.is_empty()
+            || !self.removed.is_empty()
+            || !self.removed_strings.is_empty()
+            || !self.modified_strings.is_empty()
+            || self
+                .modified
+                .iter()
+                .flat_map(|x| &x.changes)
+                .any(|x| x.is_breaking())
+    }
+}
+
+/// Lists the meta settings that changed between two maps
+fn meta_changes(old: &ULogSystemMeta, new: &ULogSystemMeta) -> Vec<MetaChange> {
+    let mut changes = vec![];
+    if old.header_version != new.header_version {
+        changes.push(MetaChange::HeaderVersion {
+            old: old.header_version,
+            new: new.header_version,
+        });
+    }
+    if old.tick_rate != new.tick_rate {
+        changes.push(MetaChange::TickRate {
+            old: old.tick_rate,
+            new: new.tick_rate,
+        });
+    }
+    if old.crc != new.crc {
+        changes.push(MetaChange::Crc {
+            old: old.crc.map(|x| x.width()),
+            new: new.crc.map(|x| x.width()),
+        });
+    }
+    if old.endianness != new.endianness {
+        changes.push(MetaChange::Endianness {
+            old: old.endianness,
+            new: new.endianness,
+        });
+    }
+    changes
+}
+
+/// Lists what changed between two definitions of a message
+fn message_changes(old: CatalogMessage, new: CatalogMessage) -> Vec<MessageChange> {
+    let mut changes = vec![];
+    if old.format != new.format {
+        changes.push(MessageChange::Format {
+            old: old.format,
+            new: new.format,
+        });
+    }
+    if old.severity != new.severity {
+        changes.push(MessageChange::Severity {
+            old: old.severity,
+            new: new.severity,
+        });
+    }
+    if old.arguments != new.arguments {
+        changes.push(MessageChange::Arguments {
+            old: old.arguments,
+            new: new.arguments,
+        });
+    }
+    if old.location != new.location {
+        changes.push(MessageChange::Location {
+            old: old.location,
+            new: new.location,
+        });
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::endianness::Endianness;
+    use crate::map_diff::{MapDiff, MessageChange, MetaChange, ModifiedString};
+    use crate::severity::SeverityLevel;
+    use crate::test_support::{SystemBuilder, location};
+    use crate::ulog_system_info::{ULogSystemInfo, ULogSystemMeta};
+    use std::sync::Arc;
+
+    fn system(messages: &[(u16, &str, usize)]) -> ULogSystemInfo {
+        messages
+            .iter()
+            .fold(SystemBuilder::new(0x0102), |system, (id, format, line)| {
+                let location = location("main.c", *line);
+                system.message_at(*id, format, &[], location, SeverityLevel::Info)
+            })
+            .build()
+    }
+
+    #[test]
+    fn diff() {
+        let old = system(&[(0, "boot", 10), (1, "idle", 20), (2, "stop", 30)]);
+
+        let moved = system(&[(0, "boot", 10), (1, "idle", 22), (2, "stop", 30)]);
+        let diff = MapDiff::new(&old, &moved);
+        assert!(!diff.is_empty());
+        assert!(!diff.is_breaking());
+        assert_eq!(diff.modified[0].id, 1);
+
+        let new = system(&[(0, "boot", 10), (1, "running", 20), (3, "fault", 40)]);
+        let diff = MapDiff::new(&old, &new);
+        assert!(diff.is_breaking());
+        assert_eq!(diff.added[0].id, 3);
+        assert_eq!(diff.removed[0].id, 2);
+        assert_eq!(
+            diff.modified[0].changes,
+            vec![MessageChange::Format {
+                old: "idle".to_string(),
+                new: "running".to_string()
+            }]
+        );
+
+        assert!(MapDiff::new(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn meta_and_strings() {
+        let system = |strings: &[(u16, &str)], endianness| {
+            let meta = ULogSystemMeta {
+                endianness,
+                ..Default::default()
+            };
+            strings
+                .iter()
+                .fold(SystemBuilder::new(0x0102), |system, (id, string)| {
+                    system.string(*id, string, location("main.c", 1))
+                })
+                .meta(meta)
+                .build()
+        };
+        let old = system(&[(0, "idle"), (1, "run")], None);
+
+        let added = system(&[(0, "idle"), (1, "run"), (2, "stop")], None);
+        let diff = MapDiff::new(&old, &added);
+        assert_eq!(diff.added_strings[0].id, 2);
+        assert!(!diff.is_breaking());
+
+        let new = system(&[(0, "idle"), (1, "running")], Some(Endianness::Little));
+        let diff = MapDiff::new(&old, &new);
+        assert!(diff.is_breaking());
+        assert_eq!(
+            diff.meta,
+            vec![MetaChange::Endianness {
+                old: None,
+                new: Some(Endianness::Little)
+            }]
+        );
+        assert_eq!(
+            diff.modified_strings,
+            vec![ModifiedString {
+                id: 1,
+                old: Arc::new("run".to_string()),
+                new: Arc::new("running".to_string())
+            }]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use crate::decoder::FrameRecognizer;
+use crate::framing::{fill_buf, incomplete_frame};
+use std::io::BufRead;
+use std::ops::Range;
+
+/// Longest line of console text, longer lines are returned in pieces
+pub const MAX_LINE_LENGTH: usize = 1024;
+
+/// Most bytes read while looking for the end of a frame, longer frames are returned cut and fail
+/// to decode
+pub const MAX_FRAME_LENGTH: usize = 65536;
+
+/// Part of a stream mixing console text and frames, as a range of the buffer it was read into
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ConsoleChunk {
+    /// Line of console text, including its line ending
+    Text(Range<usize>),
+    /// Frame, without its delimiter
+    Frame(Range<usize>),
+    /// Console text without a line ending, such as a prompt, directly followed by a frame
+    Prompt {
+        text: Range<usize>,
+        frame: Range<usize>,
+    },
+}
+
+/// Length of the start of `data` that can be plain console text: UTF-8 without control characters
+/// other than whitespace and the escape character used by ANSI color codes. A character cut at the
+/// start is accepted, since long lines are returned in pieces
+fn text_length(data: &[u8]) -> usize {
+    let start = data
+        .iter()
+        .take(3)
+        .take_while(|x| (0x80..0xC0).contains(*x))
+        .count();
+    let data = &data[start..];
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        // Unwrap is safe here because the data is valid up to that point
+        Err(err) => std::str::from_utf8(&data[..err.valid_up_to()]).unwrap(),
+    };
+    let end = text
+        .char_indices()
+        .find(|(_, x)| x.is_control() && !matches!(x, '\t' | '\r' | '\n' | '\x1B'))
+        .map_or(text.len(), |(i, _)| i);
+    start + end
+}
+
+/// Checks whether bytes can be plain console text, see [`text_length`]. A character cut at the end
+/// is accepted as well
+fn is_text(data: &[u8]) -> bool {
+    let length = text_length(data);
+    length == data.len()
+        || std::str::from_utf8(&data[length..])
+            .is_err_and(|err| err.valid_up_to() == 0 && err.error_len().is_none())
+}
+
+/// Tells apart a frame from console text glued in front of it, which happens when a line doesn't
+/// end with a newline. The frame is taken to start after the shortest text whose removal leaves a
+/// frame of a known system, the whole chunk stays a frame when there isn't one
+fn split_prompt(recognizer: &FrameRecognizer, frame: &[u8]) -> ConsoleChunk {
+    if recognizer.recognizes(frame) {
+        return ConsoleChunk::Frame(0..frame.len());
+    }
+    (1..=text_length(frame))
+        .find(|x| recognizer.recognizes(&frame[*x..]))
+        .map_or(ConsoleChunk::Frame(0..frame.len()), |x| {
+            ConsoleChunk::Prompt {
+                text: 0..x,
+                frame: x..frame.len(),
+            }
+        })
+}
+
+/// Reads the next line of console text or frame of a stream mixing both, such as a serial port
+/// also used by a bootloader, into `buf`. Lines are returned as soon as their newline arrives.
+/// Newlines only end a line while what was read so far can be text, otherwise they are part of a
+/// frame running up to the framing delimiter. Returns None once the stream has ended
+///
+/// Text without a newline, such as a prompt, is only told apart from the frame following it by
+/// finding where a frame recognized by `recognizer` starts. Framings without a delimiter can't tell
+/// text apart from frames, their frames are read as usual
+pub fn read_console_chunk<R: BufRead>(
+    recognizer: &FrameRecognizer,
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<Option<ConsoleChunk>> {
+    let framing = recognizer.framing();
+    let Some(delimiter) = framing.delimiter() else {
+        return Ok(framing.read_frame(reader, buf)?.map(ConsoleChunk::Frame));
+    };
+
+    buf.clear();
+    let mut text = true;
+    loop {
+        let data = fill_buf(reader)?;
+        if data.is_empty() {
+            // The stream ended, only text can end without a delimiter
+            return match buf.is_empty() {
+                true => Ok(None),
+                false if text => Ok(Some(ConsoleChunk::Text(0..buf.len()))),
+                false => Err(incomplete_frame()),
+            };
+        }
+
+        let limit = match text {
+            true => MAX_LINE_LENGTH,
+            false => MAX_FRAME_LENGTH,
+        };
+        let room = limit - buf.len();
+        let end = data
+            .iter()
+            .take(room)
+            .position(|x| *x == delimiter || *x == b'\n');
+        let size = end.map_or(data.len().min(room), |x| x + 1);
+        buf.extend_from_slice(&data[..size]);
+        reader.consume(size);
+        // Text can't become text again after something that isn't
+        text = text && is_text(buf);
+
+        if end.is_some() && buf.last() == Some(&delimiter) {
+            // Skip the empty frames, left by a delimiter right after a line of text or by SLIP
+            // senders starting frames with an END byte as well
+            if buf.len() == 1 {
+                buf.clear();
+                text = true;
+                continue;
+            }
+            return Ok(Some(split_prompt(recognizer, &buf[..buf.len() - 1])));
+        }
+        if text && (end.is_some() || buf.len() == MAX_LINE_LENGTH) {
+            return Ok(Some(ConsoleChunk::Text(0..buf.len())));
+        }
+        if buf.len() == MAX_FRAME_LENGTH {
+            return Ok(Some(ConsoleChunk::Frame(0..buf.len())));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decoder::Decoder;
+    use crate::mixed_stream::{ConsoleChunk, MAX_LINE_LENGTH, read_console_chunk};
+    use crate::test_support::SystemBuilder;
+    use std::io::BufRead;
+
+    fn chunks(mut stream: impl BufRead) -> Vec<(bool, Vec<u8>)> {
+        let mut decoder = Decoder::new();
+        let system = SystemBuilder::new(0x0102).message(1, "ready", &[]);
+        decoder.add_system(system.build()).unwrap();
+        let recognizer = decoder.recognizer();
+
+        let mut buf = vec![];
+        let mut chunks = vec![];
+        while let Some(chunk) = read_console_chunk(&recognizer, &mut stream, &mut buf).unwrap() {
+            match chunk {
+                ConsoleChunk::Text(x) => chunks.push((true, buf[x].to_vec())),
+                ConsoleChunk::Frame(x) => chunks.push((false, buf[x].to_vec())),
+                ConsoleChunk::Prompt { text, frame } => {
+                    chunks.push((true, buf[text].to_vec()));
+                    chunks.push((false, buf[frame].to_vec()));
+                }
+            }
+        }
+        chunks
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!(
+            chunks(&b"boot\r\n\x00\xC3\xA9t\xC3\xA9\n\x05\x0A\x01\x00end"[..]),
+            vec![
+                (true, b"boot\r\n".to_vec()),
+                (true, "été\n".as_bytes().to_vec()),
+                // The newline is part of the frame since what comes before it isn't text
+                (false, b"\x05\x0A\x01".to_vec()),
+                (true, b"end".to_vec()),
+            ]
+        );
+
+        // A prompt without a newline is split off the frame that follows it
+        let frame = rzcobs::encode(&[0x01, 0x02, 0x00, 0x01]);
+        let mut stream = b"boot ok\r\nU-Boot> ".to_vec();
+        stream.extend_from_slice(&frame);
+        stream.push(0x00);
+        assert_eq!(
+            chunks(&stream[..]),
+            vec![
+                (true, b"boot ok\r\n".to_vec()),
+                (true, b"U-Boot> ".to_vec()),
+                (false, frame),
+            ]
+        );
+
+        // Long lines are cut, even in the middle of a character
+        let mut line = vec![b'a'; MAX_LINE_LENGTH - 1];
+        line.extend_from_slice("é\n".as_bytes());
+        let chunks = chunks(&line[..]);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|(text, _)| *text));
+        assert_eq!(chunks[0].1.len(), MAX_LINE_LENGTH);
+    }
+}
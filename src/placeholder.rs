@@ -0,0 +1,152 @@
+use crate::dwarf::{EnumType, EnumTypeMap};
+use crate::symbolizer::Symbolizer;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+/// Extra display information a placeholder carries in its format spec, on top of what the
+/// formatting library understands
+#[derive(Clone, PartialEq, Eq)]
+pub enum Annotation {
+    /// `enum(NAME)`, the value is an enumerator of the enum type NAME. The type is resolved
+    /// against the debug info once it is loaded
+    Enum {
+        name: String,
+        ty: Option<Arc<EnumType>>,
+    },
+    /// `sym`, the value is an address of the target, described by the symbol and source location
+    /// it points to
+    Symbol { symbolizer: Option<Arc<Symbolizer>> },
+}
+
+impl Debug for Annotation {
+    /// Only prints the kind of annotation, the debug info it refers to can be huge
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Annotation::Enum { name, .. } => write!(f, "Enum({name})"),
+            Annotation::Symbol { .. } => write!(f, "Symbol"),
+        }
+    }
+}
+
+impl Annotation {
+    /// Resolves the debug info this annotation refers to
+    pub fn resolve(&mut self, enum_types: &EnumTypeMap, symbolizer: &Arc<Symbolizer>) {
+        match self {
+            Annotation::Enum { name, ty } => *ty = enum_types.get(name).cloned(),
+            Annotation::Symbol { symbolizer: x } => *x = Some(symbolizer.clone()),
+        }
+    }
+}
+
+/// Placeholder of a format string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    /// Text of the placeholder without its annotation, braces included
+    pub text: String,
+    pub annotation: Option<Annotation>,
+}
+
+/// Removes the annotations from the placeholders of a format string, since the formatting
+/// library would reject them. Returns the remaining format string along with the annotation of
+/// every placeholder, in order
+pub fn strip_annotations(format: &str) -> (String, Vec<Option<Annotation>>) {
+    let (stripped, placeholders) = split_placeholders(format);
+    let annotations = placeholders.into_iter().map(|x| x.annotation).collect();
+    (stripped, annotations)
+}
+
+/// Lists the placeholders of a format string, in order
+pub fn placeholders(format: &str) -> Vec<Placeholder> {
+    split_placeholders(format).1
+}
+
+/// Removes the annotations from the placeholders of a format string, returns the remaining
+/// format string and every placeholder
+fn split_placeholders(format: &str) -> (String, Vec<Placeholder>) {
+    let mut stripped = String::with_capacity(format.len());
+    let mut placeholders = vec![];
+    let mut rest = format;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        stripped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // Escaped braces are kept as is
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            stripped.push_str(&rest[..2]);
+            rest = &rest[2..];
+            continue;
+        }
+        // A lone closing brace or an unterminated placeholder, leave it to the formatting
+        // library to complain about it
+        let Some(end) = rest.find('}').filter(|_| rest.starts_with('{')) else {
+            stripped.push_str(&rest[..1]);
+            rest = &rest[1..];
+            continue;
+        };
+
+        let placeholder = &rest[..=end];
+        rest = &rest[end + 1..];
+        let extracted = placeholder
+            .find(':')
+            .and_then(|x| Some((x, extract_annotation(&placeholder[x..])?)));
+        let placeholder = match extracted {
+            Some((spec_start, (spec, annotation))) => Placeholder {
+                text: placeholder[..spec_start].to_string() + &spec,
+                annotation: Some(annotation),
+            },
+            None => Placeholder {
+                text: placeholder.to_string(),
+                annotation: None,
+            },
+        };
+        stripped.push_str(&placeholder.text);
+        placeholders.push(placeholder);
+    }
+    stripped.push_str(rest);
+    (stripped, placeholders)
+}
+
+/// Removes the annotation from a format spec, which includes its closing brace. Returns the
+/// remaining spec and the annotation
+fn extract_annotation(spec: &str) -> Option<(String, Annotation)> {
+    if let Some(start) = spec.find("enum(") {
+        let length = spec[start..].find(')')?;
+        let name = spec[start + "enum(".len()..start + length]
+            .trim()
+            .to_string();
+        let spec = spec[..start].to_string() + &spec[start + length + 1..];
+        return Some((spec, Annotation::Enum { name, ty: None }));
+    }
+    // The symbol annotation takes the place of the type
+    let spec = spec.strip_suffix("sym}")?.to_string() + "}";
+    Some((spec, Annotation::Symbol { symbolizer: None }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::placeholder::{Annotation, strip_annotations};
+
+    #[test]
+    fn strip() {
+        let (format, annotations) =
+            strip_annotations("{{state}} {:enum(motor_state_t)} {:>8} {:#xenum( error_t )} {:sym}");
+        assert_eq!(format, "{{state}} {:} {:>8} {:#x} {:}");
+        assert_eq!(
+            annotations,
+            vec![
+                Some(Annotation::Enum {
+                    name: "motor_state_t".to_string(),
+                    ty: None
+                }),
+                None,
+                Some(Annotation::Enum {
+                    name: "error_t".to_string(),
+                    ty: None
+                }),
+                Some(Annotation::Symbol { symbolizer: None }),
+            ]
+        );
+        assert_eq!(format!("{:?}", annotations[3]), "Some(Symbol)");
+    }
+}
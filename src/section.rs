@@ -0,0 +1,107 @@
+use elf::ElfStream;
+use elf::abi::{ELFCOMPRESS_ZLIB, ELFCOMPRESS_ZSTD};
+use elf::endian::AnyEndian;
+use elf::section::SectionHeader;
+use flate2::read::ZlibDecoder;
+use ruzstd::decoding::StreamingDecoder;
+use snafu::{Backtrace, ResultExt, Snafu, ensure};
+use std::io::{Read, Seek};
+
+#[derive(Snafu, Debug)]
+pub enum SectionReadError {
+    #[snafu(display("The ELF file could not be parsed"))]
+    ElfParse {
+        backtrace: Backtrace,
+        source: elf::ParseError,
+    },
+    #[snafu(display("Unsupported compression type {compression}"))]
+    UnsupportedCompression {
+        backtrace: Backtrace,
+        compression: u32,
+    },
+    #[snafu(display("Failed to decompress the section"))]
+    Decompress {
+        backtrace: Backtrace,
+        source: std::io::Error,
+    },
+    #[snafu(display("Decompressed section is {actual} bytes long instead of {expected}"))]
+    SizeMismatch {
+        backtrace: Backtrace,
+        expected: u64,
+        actual: usize,
+    },
+}
+
+/// Reads the contents of a section, decompressing them if the section is compressed with zlib or
+/// zstd
+pub fn read_section<R: Read + Seek>(
+    elf_file: &mut ElfStream<AnyEndian, R>,
+    header: &SectionHeader,
+) -> Result<Vec<u8>, SectionReadError> {
+    let (data, compression_header) = elf_file.section_data(header).context(ElfParseSnafu)?;
+    match compression_header {
+        Some(x) => decompress(x.ch_type, x.ch_size, data),
+        None => Ok(data.to_owned()),
+    }
+}
+
+/// Decompresses the data of a section compressed with `compression`, whose header claims it
+/// decompresses to `size` bytes. The size comes from the file, so nothing is allocated up front
+/// and at most one byte more than that is decompressed, enough to tell it is wrong
+fn decompress(compression: u32, size: u64, data: &[u8]) -> Result<Vec<u8>, SectionReadError> {
+    let decoder: Box<dyn Read> = match compression {
+        ELFCOMPRESS_ZLIB => Box::new(ZlibDecoder::new(data)),
+        ELFCOMPRESS_ZSTD => Box::new(
+            StreamingDecoder::new(data)
+                .map_err(std::io::Error::other)
+                .context(DecompressSnafu)?,
+        ),
+        compression => return UnsupportedCompressionSnafu { compression }.fail(),
+    };
+
+    let mut decompressed = vec![];
+    decoder
+        .take(size.saturating_add(1))
+        .read_to_end(&mut decompressed)
+        .context(DecompressSnafu)?;
+    ensure!(
+        decompressed.len() as u64 == size,
+        SizeMismatchSnafu {
+            expected: size,
+            actual: decompressed.len(),
+        }
+    );
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::section::{SectionReadError, decompress};
+    use elf::abi::{ELFCOMPRESS_ZLIB, ELFCOMPRESS_ZSTD};
+    use flate2::Compression;
+    use flate2::read::ZlibEncoder;
+    use ruzstd::encoding::{CompressionLevel, compress_to_vec};
+    use std::io::Read;
+
+    #[test]
+    fn round_trip() {
+        let data = b"ulog strings ".repeat(100);
+        let mut zlib = vec![];
+        ZlibEncoder::new(&data[..], Compression::default())
+            .read_to_end(&mut zlib)
+            .unwrap();
+        let zstd = compress_to_vec(&data[..], CompressionLevel::Fastest);
+
+        for (compression, compressed) in [(ELFCOMPRESS_ZLIB, zlib), (ELFCOMPRESS_ZSTD, zstd)] {
+            let size = data.len() as u64;
+            assert_eq!(decompress(compression, size, &compressed).unwrap(), data);
+            // The header lies about the size either way
+            for size in [size - 1, size + 1] {
+                assert!(matches!(
+                    decompress(compression, size, &compressed),
+                    Err(SectionReadError::SizeMismatch { .. })
+                ));
+            }
+        }
+    }
+}
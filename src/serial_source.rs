@@ -0,0 +1,291 @@
+use crate::decoder::FrameRecognizer;
+use crate::framing::Framing;
+use crate::source::{ReconnectingSource, SourceEvent};
+use crate::util::glob_match;
+use serialport::{
+    ClearBuffer, SerialPort, SerialPortBuilder, SerialPortInfo, SerialPortType, UsbPortInfo,
+};
+use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
+use std::io::{ErrorKind, Read};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Delay between two attempts at finding the port again
+const RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// Baud rates tried when detecting the baud rate, most common first
+pub const COMMON_BAUDRATES: &[u32] = &[
+    115200, 38400, 9600, 57600, 19200, 230400, 460800, 921600, 1000000, 2000000,
+];
+
+/// Source that reads from a serial port and reopens it when it disappears, for example when a
+/// USB adapter is unplugged or the board it is part of resets
+pub struct SerialSource {
+    source: ReconnectingSource<Box<dyn SerialPort>>,
+}
+
+impl SerialSource {
+    /// Opens the port `port_name` using the settings of `builder`. The builder has no RTS
+    /// setting, so it is given separately as `rts` and applied every time the port is opened
+    pub fn open(
+        builder: SerialPortBuilder,
+        port_name: &str,
+        rts: Option<bool>,
+        framing: Framing,
+        console_text: Option<FrameRecognizer>,
+    ) -> serialport::Result<Self> {
+        let port = open_port(&builder, port_name, rts)?;
+        let usb = usb_info(port_name);
+        let mut last_name = port_name.to_string();
+        let connect = move || {
+            let port_name = find_port(usb.as_ref(), &last_name).ok_or_else(|| {
+                std::io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("Serial port {last_name} not found"),
+                )
+            })?;
+            // The port can show up a little before it is ready to be opened, the source tries
+            // again later
+            let port = open_port(&builder, &port_name, rts)?;
+            last_name.clone_from(&port_name);
+            Ok((Arc::from(port_name), port))
+        };
+        let source =
+            ReconnectingSource::new(Box::new(connect), RECONNECT_DELAY, framing, console_text)
+                .with_connection(Arc::from(port_name), port);
+        Ok(Self { source })
+    }
+}
+
+impl Iterator for SerialSource {
+    type Item = SourceEvent;
+
+    /// Never returns None, the source keeps waiting for the port to come back
+    fn next(&mut self) -> Option<Self::Item> {
+        self.source.next()
+    }
+}
+
+/// Looks for a port again, preferring its USB identity over its name since USB adapters can come
+/// back under a different name
+fn find_port(usb: Option<&UsbPortInfo>, port_name: &str) -> Option<String> {
+    let ports = serialport::available_ports().ok()?;
+    let port = match usb {
+        Some(usb) => ports.into_iter().find(|x| {
+            matches!(&x.port_type, SerialPortType::UsbPort(info)
+                if info.vid == usb.vid && info.pid == usb.pid && info.serial_number == usb.serial_number)
+        }),
+        None => ports.into_iter().find(|x| x.port_name == port_name),
+    };
+    port.map(|x| x.port_name)
+}
+
+/// Detects the baud rate of the device on `port_name` by listening at each of `rates` in turn for
+/// `window`. The port is opened once and switched from rate to rate. Every rate is scored with
+/// [`baudrate_score`], the best one wins. Returns None when no rate received a single accepted
+/// frame
+pub fn detect_baudrate(
+    builder: &SerialPortBuilder,
+    port_name: &str,
+    rates: &[u32],
+    window: Duration,
+    framing: Framing,
+    mut is_frame: impl FnMut(&[u8]) -> bool,
+) -> serialport::Result<Option<u32>> {
+    let Some(&first) = rates.first() else {
+        return Ok(None);
+    };
+    let mut port = builder
+        .clone()
+        .path(port_name)
+        .baud_rate(first)
+        .timeout(window / 10)
+        .open()?;
+
+    let mut best = None;
+    let mut best_score = 0;
+    for &rate in rates {
+        port.set_baud_rate(rate)?;
+        // Drop what was received at the previous rate
+        port.clear(ClearBuffer::Input)?;
+
+        let mut data = vec![];
+        let mut buf = [0; 1024];
+        let start = Instant::now();
+        while start.elapsed() < window {
+            match port.read(&mut buf) {
+                Ok(size) => data.extend_from_slice(&buf[..size]),
+                Err(err) if err.kind() == ErrorKind::TimedOut => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let score = baudrate_score(&data, framing, &mut is_frame);
+        if score > best_score {
+            best = Some(rate);
+            best_score = score;
+        }
+    }
+    Ok(best)
+}
+
+/// Scores the data received at a baud rate by how many of its frames, split according to
+/// `framing`, are accepted by `is_frame`. Data received at the wrong rate is garbage that splits
+/// into frames no system knows
+pub fn baudrate_score(
+    data: &[u8],
+    framing: Framing,
+    mut is_frame: impl FnMut(&[u8]) -> bool,
+) -> usize {
+    framing
+        .split(data)
+        .into_iter()
+        .filter(|x| is_frame(x))
+        .count()
+}
+
+/// Opens a port, setting its RTS state if one is given
+fn open_port(
+    builder: &SerialPortBuilder,
+    port_name: &str,
+    rts: Option<bool>,
+) -> serialport::Result<Box<dyn SerialPort>> {
+    let mut port = builder.clone().path(port_name).open()?;
+    if let Some(rts) = rts {
+        port.write_request_to_send(rts)?;
+    }
+    Ok(port)
+}
+
+/// Gets the USB information of a port, if it is a USB port
+fn usb_info(port_name: &str) -> Option<UsbPortInfo> {
+    serialport::available_ports()
+        .ok()?
+        .into_iter()
+        .find(|x| x.port_name == port_name)
+        .and_then(|x| match x.port_type {
+            SerialPortType::UsbPort(info) => Some(info),
+            _ => None,
+        })
+}
+
+#[derive(Snafu, Debug)]
+pub enum SerialMatcherParseError {
+    #[snafu(display("Expected <KEY>=<VALUE>, got '{criterion}'"))]
+    MissingValue {
+        backtrace: Backtrace,
+        criterion: String,
+    },
+    #[snafu(display("Unknown key '{key}', expected vid, pid, serial, manufacturer or product"))]
+    UnknownKey { backtrace: Backtrace, key: String },
+    #[snafu(display("Invalid USB id '{value}'"))]
+    InvalidId {
+        backtrace: Backtrace,
+        source: std::num::ParseIntError,
+        value: String,
+    },
+}
+
+/// Criteria a USB serial port must meet to be selected. String criteria are case insensitive
+/// globs where `*` matches any number of characters and `?` matches a single character
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SerialMatcher {
+    pub vid: Option<u16>,
+    pub pid: Option<u16>,
+    pub serial_number: Option<String>,
+    pub manufacturer: Option<String>,
+    pub product: Option<String>,
+}
+
+impl SerialMatcher {
+    /// Checks whether a port meets every criteria, ports that aren't USB ports never do
+    pub fn matches(&self, port: &SerialPortInfo) -> bool {
+        let SerialPortType::UsbPort(info) = &port.port_type else {
+            return false;
+        };
+        let glob_matches = |pattern: &Option<String>, value: &Option<String>| match pattern {
+            None => true,
+            Some(pattern) => value.as_ref().is_some_and(|x| glob_match(pattern, x)),
+        };
+
+        self.vid.is_none_or(|x| x == info.vid)
+            && self.pid.is_none_or(|x| x == info.pid)
+            && glob_matches(&self.serial_number, &info.serial_number)
+            && glob_matches(&self.manufacturer, &info.manufacturer)
+            && glob_matches(&self.product, &info.product)
+    }
+}
+
+impl FromStr for SerialMatcher {
+    type Err = SerialMatcherParseError;
+
+    /// Parses a comma separated list of criteria, such as `vid=0483,pid=5740,serial=ABC*`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut matcher = SerialMatcher::default();
+        for criterion in s.split(',').filter(|x| !x.is_empty()) {
+            let (key, value) = criterion
+                .split_once('=')
+                .context(MissingValueSnafu { criterion })?;
+            let parse_id = |value: &str| {
+                u16::from_str_radix(value.trim_start_matches("0x"), 16)
+                    .context(InvalidIdSnafu { value })
+            };
+
+            match key.trim() {
+                "vid" => matcher.vid = Some(parse_id(value)?),
+                "pid" => matcher.pid = Some(parse_id(value)?),
+                "serial" => matcher.serial_number = Some(value.to_string()),
+                "manufacturer" => matcher.manufacturer = Some(value.to_string()),
+                "product" => matcher.product = Some(value.to_string()),
+                key => return UnknownKeySnafu { key }.fail(),
+            }
+        }
+        Ok(matcher)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framing::Framing;
+    use crate::serial_source::{SerialMatcher, baudrate_score};
+    use serialport::{SerialPortInfo, SerialPortType, UsbPortInfo};
+
+    #[test]
+    fn score() {
+        let is_frame = |x: &[u8]| x.first() == Some(&0x01);
+        let data = b"\x01\x02\x00\xFF\x00\x01\x00";
+        assert_eq!(baudrate_score(data, Framing::Rzcobs, is_frame), 2);
+        assert_eq!(baudrate_score(data, Framing::Slip, is_frame), 1);
+        assert_eq!(baudrate_score(b"\xF0\xFE", Framing::Rzcobs, is_frame), 0);
+    }
+
+    #[test]
+    fn matcher() {
+        let matcher: SerialMatcher = "vid=0483,pid=5740,serial=ABC*,product=*ST-Link*"
+            .parse()
+            .unwrap();
+        assert_eq!(matcher.vid, Some(0x0483));
+        assert_eq!(matcher.pid, Some(0x5740));
+
+        let mut port = SerialPortInfo {
+            port_name: "/dev/ttyACM0".to_string(),
+            port_type: SerialPortType::UsbPort(UsbPortInfo {
+                vid: 0x0483,
+                pid: 0x5740,
+                serial_number: Some("ABC123".to_string()),
+                manufacturer: None,
+                product: Some("STM32 ST-Link".to_string()),
+            }),
+        };
+        assert!(matcher.matches(&port));
+        port.port_type = SerialPortType::Unknown;
+        assert!(!matcher.matches(&port));
+    }
+
+    #[test]
+    #[should_panic = "UnknownKey"]
+    fn unknown_key() {
+        "vendor=0483".parse::<SerialMatcher>().unwrap();
+    }
+}
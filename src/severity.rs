@@ -1,9 +1,11 @@
 use owo_colors::OwoColorize;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use snafu::{Backtrace, Snafu};
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 
 /// Enum representing a severity level
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum SeverityLevel {
     Emergency = 0,
     Alert = 1,
@@ -20,6 +22,8 @@ pub enum SeverityLevel {
 pub enum SeverityLevelParseError {
     #[snafu(display("Unknown value ({value}) for severity level"))]
     UnknownValue { value: usize, backtrace: Backtrace },
+    #[snafu(display("Unknown severity level '{name}'"))]
+    UnknownName { name: String, backtrace: Backtrace },
 }
 
 impl TryFrom<usize> for SeverityLevel {
@@ -43,6 +47,27 @@ impl TryFrom<usize> for SeverityLevel {
     }
 }
 
+impl FromStr for SeverityLevel {
+    type Err = SeverityLevelParseError;
+
+    /// Parses the name of a severity level, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = match s.to_lowercase().as_str() {
+            "emergency" => Self::Emergency,
+            "alert" => Self::Alert,
+            "critical" => Self::Critical,
+            "error" => Self::Error,
+            "warning" => Self::Warning,
+            "notice" => Self::Notice,
+            "info" => Self::Info,
+            "debug" => Self::Debug,
+            "trace" => Self::Trace,
+            _ => return UnknownNameSnafu { name: s }.fail(),
+        };
+        Ok(value)
+    }
+}
+
 impl Display for SeverityLevel {
     /// Print the severity level to text form. Use alternate display to have colors
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -77,3 +102,19 @@ impl Display for SeverityLevel {
         }
     }
 }
+
+impl Serialize for SeverityLevel {
+    /// Serializes the severity level as its uncolored text form
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for SeverityLevel {
+    /// Deserializes the severity level from its text form
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
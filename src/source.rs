@@ -0,0 +1,188 @@
+use crate::decoder::FrameRecognizer;
+use crate::frame_reader::{FrameReader, RawFrame};
+use crate::framing::Framing;
+use std::io::{BufReader, Read};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Event produced by a uLog stream source
+#[derive(Debug)]
+pub enum SourceEvent {
+    /// A frame was received
+    Frame(RawFrame),
+    /// Reading from the source failed
+    Error(std::io::Error),
+    /// A connection to a peer was established
+    Connected(Arc<str>),
+    /// The connection to a peer was lost
+    Disconnected(Arc<str>),
+}
+
+impl From<std::io::Result<RawFrame>> for SourceEvent {
+    fn from(value: std::io::Result<RawFrame>) -> Self {
+        match value {
+            Ok(frame) => SourceEvent::Frame(frame),
+            Err(err) => SourceEvent::Error(err),
+        }
+    }
+}
+
+/// Establishes a connection, returns the name of the peer and the stream to read from it
+pub type Connect<R> = Box<dyn FnMut() -> std::io::Result<(Arc<str>, R)>>;
+
+/// Source that reads frames from a connection and establishes a new one with its `connect`
+/// function whenever the connection is lost
+pub struct ReconnectingSource<R: Read> {
+    connect: Connect<R>,
+    /// Delay between two failed connection attempts
+    delay: Duration,
+    /// How frames are delimited in the stream
+    framing: Framing,
+    /// Recognizer of the frames, set when console text is expected between them
+    console_text: Option<FrameRecognizer>,
+    /// Name of the peer and frames of the current connection, None while disconnected
+    connection: Option<(Arc<str>, FrameReader<BufReader<R>>)>,
+    /// Instant the monotonic timestamps are relative to, shared by all connections
+    start: Instant,
+    /// Whether the last connection attempt failed, to only report the first failure in a row
+    failing: bool,
+    /// Event to return before reading anything else
+    pending: Option<SourceEvent>,
+}
+
+impl<R: Read> ReconnectingSource<R> {
+    /// Creates the source, the connection is only established once the first event is requested
+    pub fn new(
+        connect: Connect<R>,
+        delay: Duration,
+        framing: Framing,
+        console_text: Option<FrameRecognizer>,
+    ) -> Self {
+        Self {
+            connect,
+            delay,
+            framing,
+            console_text,
+            connection: None,
+            start: Instant::now(),
+            failing: false,
+            pending: None,
+        }
+    }
+
+    /// Reads from an already established connection to `peer` before connecting again
+    pub fn with_connection(mut self, peer: Arc<str>, stream: R) -> Self {
+        self.connection = Some((peer, self.frames(stream)));
+        self
+    }
+
+    fn frames(&self, stream: R) -> FrameReader<BufReader<R>> {
+        FrameReader::with_start(BufReader::new(stream), self.start)
+            .with_framing(self.framing)
+            .with_console_text(self.console_text.clone())
+    }
+}
+
+impl<R: Read> Iterator for ReconnectingSource<R> {
+    type Item = SourceEvent;
+
+    /// Never returns None, the source keeps trying to reconnect
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.pending.take() {
+            return Some(event);
+        }
+
+        while self.connection.is_none() {
+            // Not connected, try to connect again
+            if self.failing {
+                thread::sleep(self.delay);
+            }
+            match (self.connect)() {
+                Ok((peer, stream)) => {
+                    self.failing = false;
+                    self.connection = Some((peer.clone(), self.frames(stream)));
+                    return Some(SourceEvent::Connected(peer));
+                }
+                // Only the first error is reported, the rest would just be noise
+                Err(err) if !self.failing => {
+                    self.failing = true;
+                    return Some(SourceEvent::Error(err));
+                }
+                Err(_) => {}
+            }
+        }
+
+        // Unwrap is safe here because the loop above only exits once connected
+        let (peer, frames) = self.connection.as_mut().unwrap();
+        match frames.next() {
+            // EOF, the peer closed the connection
+            None => {
+                let peer = peer.clone();
+                self.connection = None;
+                Some(SourceEvent::Disconnected(peer))
+            }
+            // The connection is unusable after an error, report the disconnection right after.
+            // Timeouts are already retried by the frame reader
+            Some(Err(err)) => {
+                self.pending = Some(SourceEvent::Disconnected(peer.clone()));
+                self.connection = None;
+                Some(SourceEvent::Error(err))
+            }
+            Some(Ok(frame)) => Some(SourceEvent::Frame(frame)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framing::Framing;
+    use crate::source::{ReconnectingSource, SourceEvent};
+    use std::collections::VecDeque;
+    use std::io::ErrorKind;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn reconnect() {
+        let mut connections = VecDeque::from([
+            Ok("b"),
+            Err(ErrorKind::ConnectionRefused),
+            Err(ErrorKind::ConnectionRefused),
+            Ok("c"),
+        ]);
+        let connect = move || match connections.pop_front().unwrap() {
+            Ok(peer) => Ok((Arc::from(peer), &b"\x01\x00"[..])),
+            Err(kind) => Err(kind.into()),
+        };
+        let source =
+            ReconnectingSource::new(Box::new(connect), Duration::ZERO, Framing::Rzcobs, None)
+                .with_connection(Arc::from("a"), &b"\x01\x00\x02"[..]);
+
+        let events = source
+            .take(9)
+            .map(|x| match x {
+                SourceEvent::Frame(frame) => format!("frame {:?}", frame.data),
+                SourceEvent::Error(err) => format!("error {:?}", err.kind()),
+                SourceEvent::Connected(peer) => format!("connected {peer}"),
+                SourceEvent::Disconnected(peer) => format!("disconnected {peer}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                "frame [1]",
+                // Incomplete frame
+                "error UnexpectedEof",
+                "disconnected a",
+                "connected b",
+                "frame [1]",
+                "disconnected b",
+                // Only the first failure is reported
+                "error ConnectionRefused",
+                "connected c",
+                "frame [1]",
+            ]
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use crate::dwarf::LineRow;
+use crate::location::Location;
+use std::fmt::{Debug, Formatter};
+
+/// Function or object symbol of an ELF file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolRange {
+    pub name: String,
+    pub address: u64,
+    /// Size in bytes, 0 when unknown
+    pub size: u64,
+}
+
+/// Resolves addresses of the target into symbols and source locations
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Symbolizer {
+    /// Symbols sorted by address
+    symbols: Vec<SymbolRange>,
+    /// Line table rows sorted by address
+    lines: Vec<LineRow>,
+    /// Whether the lowest bit of code addresses marks Thumb code instead of being part of the
+    /// address, as on ARM
+    thumb: bool,
+}
+
+impl Debug for Symbolizer {
+    /// Only prints the size of the tables, they hold the whole symbol table and line tables
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Symbolizer")
+            .field("symbols", &self.symbols.len())
+            .field("lines", &self.lines.len())
+            .field("thumb", &self.thumb)
+            .finish()
+    }
+}
+
+impl Symbolizer {
+    pub fn new(mut symbols: Vec<SymbolRange>, lines: Vec<LineRow>, thumb: bool) -> Self {
+        symbols.sort_by_key(|x| x.address);
+        Self {
+            symbols,
+            lines,
+            thumb,
+        }
+    }
+
+    /// Finds the symbol containing an address, along with the offset of the address in it
+    pub fn symbol(&self, address: u64) -> Option<(&SymbolRange, u64)> {
+        let address = self.strip_thumb_bit(address);
+        let index = self.symbols.partition_point(|x| x.address <= address);
+        let symbol = self.symbols.get(index.checked_sub(1)?)?;
+        let offset = address - symbol.address;
+        // Symbols without a size are assumed to extend up to the next one
+        (symbol.size == 0 || offset < symbol.size).then_some((symbol, offset))
+    }
+
+    /// Finds the source location of the code at an address
+    pub fn location(&self, address: u64) -> Option<&Location> {
+        let address = self.strip_thumb_bit(address);
+        let index = self.lines.partition_point(|x| x.address <= address);
+        self.lines.get(index.checked_sub(1)?)?.location.as_ref()
+    }
+
+    /// Describes an address as `symbol+offset (file:line)`, leaving out what isn't known about
+    /// it. Returns None when nothing is
+    pub fn describe(&self, address: u64) -> Option<String> {
+        let symbol = self.symbol(address).map(|(symbol, offset)| match offset {
+            0 => symbol.name.clone(),
+            offset => format!("{}+{offset:#x}", symbol.name),
+        });
+        let location = self
+            .location(address)
+            .map(|x| format!("({}:{})", x.file, x.line));
+        match (symbol, location) {
+            (Some(symbol), Some(location)) => Some(format!("{symbol} {location}")),
+            (Some(symbol), None) => Some(symbol),
+            (None, Some(location)) => Some(format!("{address:#x} {location}")),
+            (None, None) => None,
+        }
+    }
+
+    fn strip_thumb_bit(&self, address: u64) -> u64 {
+        if self.thumb { address & !1 } else { address }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dwarf::LineRow;
+    use crate::location::Location;
+    use crate::symbolizer::{SymbolRange, Symbolizer};
+    use std::sync::Arc;
+
+    #[test]
+    fn describe() {
+        let symbol = |name: &str, address, size| SymbolRange {
+            name: name.to_string(),
+            address,
+            size,
+        };
+        let row = |address, line: Option<usize>| LineRow {
+            address,
+            location: line.map(|line| Location {
+                file: Arc::new("uart.c".to_string()),
+                line,
+            }),
+        };
+        let symbolizer = Symbolizer::new(
+            vec![
+                symbol("handler_uart_rx", 0x8000100, 0x40),
+                symbol("main", 0x8000000, 0x100),
+            ],
+            vec![
+                row(0x8000100, Some(210)),
+                row(0x8000118, Some(214)),
+                row(0x8000140, None),
+            ],
+            true,
+        );
+
+        assert_eq!(
+            symbolizer.describe(0x800011D).as_deref(),
+            Some("handler_uart_rx+0x1c (uart.c:214)")
+        );
+        assert_eq!(
+            symbolizer.describe(0x8000100).as_deref(),
+            Some("handler_uart_rx (uart.c:210)")
+        );
+        assert_eq!(symbolizer.describe(0x8000004).as_deref(), Some("main+0x4"));
+        assert_eq!(symbolizer.describe(0x8000140), None);
+    }
+}
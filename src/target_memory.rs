@@ -0,0 +1,48 @@
+/// Contents of the loadable sections of an ELF file, as they are laid out in the memory of the
+/// target
+#[derive(Debug, Clone, Default)]
+pub struct TargetMemory {
+    /// Start address and contents of each section
+    sections: Vec<(u64, Vec<u8>)>,
+}
+
+impl TargetMemory {
+    pub fn new(sections: Vec<(u64, Vec<u8>)>) -> Self {
+        Self { sections }
+    }
+
+    /// Gets the memory from an address up to the end of the section holding it
+    pub fn data_at(&self, address: u64) -> Option<&[u8]> {
+        self.sections.iter().find_map(|(start, data)| {
+            let offset = usize::try_from(address.checked_sub(*start)?).ok()?;
+            data.get(offset..).filter(|x| !x.is_empty())
+        })
+    }
+
+    /// Reads the NUL terminated string at an address, returns None if the address isn't in a
+    /// loadable section or the string isn't terminated before the end of its section
+    pub fn read_string(&self, address: u64) -> Option<String> {
+        let data = self.data_at(address)?;
+        let length = data.iter().position(|x| *x == 0x00)?;
+        Some(String::from_utf8_lossy(&data[..length]).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::target_memory::TargetMemory;
+
+    #[test]
+    fn read_string() {
+        let memory = TargetMemory::new(vec![
+            (0x0800_0000, b"\x00\x00".to_vec()),
+            (0x0800_1000, b"idle\x00running\x00broken".to_vec()),
+        ]);
+        assert_eq!(memory.read_string(0x0800_1000).as_deref(), Some("idle"));
+        assert_eq!(memory.read_string(0x0800_1005).as_deref(), Some("running"));
+        assert_eq!(memory.read_string(0x0800_1007).as_deref(), Some("nning"));
+        assert_eq!(memory.read_string(0x0800_1001).as_deref(), Some("dle"));
+        assert_eq!(memory.read_string(0x0800_100D), None);
+        assert_eq!(memory.read_string(0x2000_0000), None);
+    }
+}
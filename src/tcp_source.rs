@@ -0,0 +1,176 @@
+use crate::decoder::FrameRecognizer;
+use crate::frame_reader::FrameReader;
+use crate::framing::Framing;
+use crate::source::{ReconnectingSource, SourceEvent};
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Delay between two connection attempts
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Source that connects to a TCP server and reconnects whenever the connection is lost
+pub struct TcpClientSource {
+    source: ReconnectingSource<TcpStream>,
+}
+
+impl TcpClientSource {
+    /// Creates the source, the connection is only established once the first event is requested
+    pub fn new(address: &str, framing: Framing, console_text: Option<FrameRecognizer>) -> Self {
+        let address: Arc<str> = Arc::from(address);
+        let connect = move || Ok((address.clone(), TcpStream::connect(&*address)?));
+        Self {
+            source: ReconnectingSource::new(
+                Box::new(connect),
+                RECONNECT_DELAY,
+                framing,
+                console_text,
+            ),
+        }
+    }
+}
+
+impl Iterator for TcpClientSource {
+    type Item = SourceEvent;
+
+    /// Never returns None, the source keeps trying to reconnect
+    fn next(&mut self) -> Option<Self::Item> {
+        self.source.next()
+    }
+}
+
+/// Source that accepts any number of TCP clients and decodes each of them as its own stream.
+/// Frames are tagged with the address of the client that sent them
+pub struct TcpServerSource {
+    /// Events sent by the client threads
+    events: Receiver<SourceEvent>,
+    /// Address the source listens on
+    local_addr: SocketAddr,
+}
+
+impl TcpServerSource {
+    /// Starts listening on `address` and accepting clients in the background
+    pub fn bind(
+        address: impl ToSocketAddrs,
+        framing: Framing,
+        console_text: Option<FrameRecognizer>,
+    ) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let local_addr = listener.local_addr()?;
+        let (sender, events) = channel();
+        let start = Instant::now();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let sender = sender.clone();
+                        let console_text = console_text.clone();
+                        thread::spawn(move || {
+                            handle_client(stream, start, framing, console_text, sender)
+                        });
+                    }
+                    Err(err) => {
+                        // Nobody is listening anymore, stop accepting clients
+                        if sender.send(SourceEvent::Error(err)).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self { events, local_addr })
+    }
+
+    /// Address the source listens on, which tells the port picked when binding port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Iterator for TcpServerSource {
+    type Item = SourceEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.events.recv().ok()
+    }
+}
+
+/// Reads frames from a client until it disconnects, forwarding everything to `sender`
+fn handle_client(
+    stream: TcpStream,
+    start: Instant,
+    framing: Framing,
+    console_text: Option<FrameRecognizer>,
+    sender: Sender<SourceEvent>,
+) {
+    let peer: Arc<str> = match stream.peer_addr() {
+        Ok(peer) => Arc::from(peer.to_string()),
+        Err(err) => {
+            let _ = sender.send(SourceEvent::Error(err));
+            return;
+        }
+    };
+
+    let _ = sender.send(SourceEvent::Connected(peer.clone()));
+    let frames = FrameReader::with_start(BufReader::new(stream), start)
+        .with_framing(framing)
+        .with_console_text(console_text)
+        .tagged(peer.clone());
+    for frame in frames {
+        // The connection is unusable after an error
+        let is_err = frame.is_err();
+        if sender.send(SourceEvent::from(frame)).is_err() || is_err {
+            break;
+        }
+    }
+    let _ = sender.send(SourceEvent::Disconnected(peer));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framing::Framing;
+    use crate::source::SourceEvent;
+    use crate::tcp_source::TcpServerSource;
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    /// Kind, source tag and data of the next event
+    fn next(source: &mut TcpServerSource) -> (&'static str, String, Vec<u8>) {
+        match source.next().unwrap() {
+            SourceEvent::Connected(peer) => ("connected", peer.to_string(), vec![]),
+            SourceEvent::Disconnected(peer) => ("disconnected", peer.to_string(), vec![]),
+            SourceEvent::Frame(frame) => ("frame", frame.source.unwrap().to_string(), frame.data),
+            SourceEvent::Error(err) => panic!("{err}"),
+        }
+    }
+
+    #[test]
+    fn clients() {
+        let mut source = TcpServerSource::bind("127.0.0.1:0", Framing::Rzcobs, None).unwrap();
+        let mut first = TcpStream::connect(source.local_addr()).unwrap();
+        let first_peer = first.local_addr().unwrap().to_string();
+        assert_eq!(next(&mut source), ("connected", first_peer.clone(), vec![]));
+        let mut second = TcpStream::connect(source.local_addr()).unwrap();
+        let second_peer = second.local_addr().unwrap().to_string();
+        assert_eq!(
+            next(&mut source),
+            ("connected", second_peer.clone(), vec![])
+        );
+
+        // Frames are tagged with the client that sent them
+        second.write_all(b"\x03\x00").unwrap();
+        assert_eq!(next(&mut source), ("frame", second_peer.clone(), vec![3]));
+        first.write_all(b"\x01\x02\x00").unwrap();
+        assert_eq!(next(&mut source), ("frame", first_peer.clone(), vec![1, 2]));
+
+        drop(first);
+        assert_eq!(next(&mut source), ("disconnected", first_peer, vec![]));
+        drop(second);
+        assert_eq!(next(&mut source), ("disconnected", second_peer, vec![]));
+    }
+}
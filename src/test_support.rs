@@ -0,0 +1,87 @@
+use crate::location::Location;
+use crate::severity::SeverityLevel;
+use crate::target_memory::TargetMemory;
+use crate::ulog_argument::ULogArgument;
+use crate::ulog_message::{ULogMessage, ULogMessageMap};
+use crate::ulog_string::{ULogString, ULogStringMap};
+use crate::ulog_system_info::{ULogSystemInfo, ULogSystemMeta};
+use std::sync::Arc;
+
+/// Creates a location in `file`
+pub fn location(file: &str, line: usize) -> Location {
+    Location {
+        file: Arc::new(file.to_string()),
+        line,
+    }
+}
+
+/// Builds the uLog map of a system the way an ELF file or dictionary would declare it
+pub struct SystemBuilder {
+    system_id: u16,
+    messages: ULogMessageMap,
+    strings: ULogStringMap,
+    meta: ULogSystemMeta,
+}
+
+impl SystemBuilder {
+    pub fn new(system_id: u16) -> Self {
+        Self {
+            system_id,
+            messages: ULogMessageMap::new(),
+            strings: ULogStringMap::new(),
+            meta: ULogSystemMeta::default(),
+        }
+    }
+
+    /// Adds an info message declared at main.c:1, whose arguments have the type ids `arguments`
+    pub fn message(self, id: u16, format: &str, arguments: &[u8]) -> Self {
+        self.message_at(
+            id,
+            format,
+            arguments,
+            location("main.c", 1),
+            SeverityLevel::Info,
+        )
+    }
+
+    /// Adds a message declared at `location`
+    pub fn message_at(
+        mut self,
+        id: u16,
+        format: &str,
+        arguments: &[u8],
+        location: Location,
+        severity: SeverityLevel,
+    ) -> Self {
+        let mut message = ULogMessage::new(id, format.to_string(), location, severity)
+            .expect("Invalid format string");
+        for &x in arguments {
+            let argument = ULogArgument::try_from(x).expect("Invalid argument type id");
+            message.arguments_mut().push(argument);
+        }
+        self.messages.insert(id, message);
+        self
+    }
+
+    /// Adds a string declared at `location`
+    pub fn string(mut self, id: u16, string: &str, location: Location) -> Self {
+        let string = ULogString::new(id, string.to_string(), location);
+        self.strings.insert(id, string);
+        self
+    }
+
+    pub fn meta(mut self, meta: ULogSystemMeta) -> Self {
+        self.meta = meta;
+        self
+    }
+
+    pub fn build(self) -> ULogSystemInfo {
+        ULogSystemInfo::new(
+            self.strings,
+            self.messages,
+            self.system_id,
+            self.meta,
+            TargetMemory::default(),
+        )
+    }
+}
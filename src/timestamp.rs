@@ -0,0 +1,38 @@
+use serde::{Serialize, Serializer};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Host side time at which a frame was received
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ReceiveTimestamp {
+    /// Wall clock time, serialized as seconds since the unix epoch
+    #[serde(serialize_with = "serialize_unix_seconds")]
+    pub wall: SystemTime,
+    /// Monotonic time elapsed since the start of the stream, serialized as seconds
+    #[serde(serialize_with = "serialize_seconds")]
+    pub monotonic: Duration,
+}
+
+impl ReceiveTimestamp {
+    /// Takes a timestamp now, with the monotonic time relative to `start`
+    pub fn now(start: Instant) -> Self {
+        Self {
+            wall: SystemTime::now(),
+            monotonic: start.elapsed(),
+        }
+    }
+}
+
+fn serialize_seconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+fn serialize_unix_seconds<S: Serializer>(
+    time: &SystemTime,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    // Times before the epoch can't come out of a clock that is set properly, clamp them to 0
+    serialize_seconds(
+        &time.duration_since(UNIX_EPOCH).unwrap_or_default(),
+        serializer,
+    )
+}
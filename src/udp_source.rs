@@ -0,0 +1,106 @@
+use crate::frame_reader::RawFrame;
+use crate::framing::Framing;
+use crate::source::SourceEvent;
+use crate::timestamp::ReceiveTimestamp;
+use std::collections::VecDeque;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Largest possible UDP payload
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+/// Source that receives frames over UDP. Every datagram holds one or more frames, the end of the
+/// datagram also ends its last frame. Frames are tagged with the sender address
+pub struct UdpSource {
+    socket: UdpSocket,
+    /// Address the socket is bound to
+    local_addr: SocketAddr,
+    /// How frames are delimited in a datagram
+    framing: Framing,
+    /// Frames of the last datagram that weren't returned yet
+    pending: VecDeque<RawFrame>,
+    /// Instant the monotonic timestamps are relative to
+    start: Instant,
+    /// Receive buffer
+    buf: Vec<u8>,
+}
+
+impl UdpSource {
+    pub fn bind(address: impl ToSocketAddrs, framing: Framing) -> std::io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        Ok(Self {
+            local_addr: socket.local_addr()?,
+            socket,
+            framing,
+            pending: VecDeque::new(),
+            start: Instant::now(),
+            buf: vec![0; MAX_DATAGRAM_SIZE],
+        })
+    }
+
+    /// Address the socket is bound to, which tells the port picked when binding port 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Iterator for UdpSource {
+    type Item = SourceEvent;
+
+    /// Never returns None, the source keeps waiting for datagrams
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() {
+            let (size, sender) = match self.socket.recv_from(&mut self.buf) {
+                Ok(x) => x,
+                Err(err) => return Some(SourceEvent::Error(err)),
+            };
+            let timestamp = ReceiveTimestamp::now(self.start);
+            let sender: Arc<str> = Arc::from(sender.to_string());
+
+            // Every frame of a datagram shares its timestamp and sender
+            self.pending.extend(
+                self.framing
+                    .split(&self.buf[..size])
+                    .into_iter()
+                    .map(|frame| RawFrame {
+                        data: frame.to_vec(),
+                        timestamp,
+                        source: Some(sender.clone()),
+                        text: false,
+                    }),
+            );
+        }
+
+        self.pending.pop_front().map(SourceEvent::Frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::framing::Framing;
+    use crate::source::SourceEvent;
+    use crate::udp_source::UdpSource;
+    use std::net::UdpSocket;
+
+    #[test]
+    fn datagram() {
+        let mut source = UdpSource::bind("127.0.0.1:0", Framing::Rzcobs).unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender
+            .send_to(b"\x01\x02\x00\x03\x04\x00", source.local_addr())
+            .unwrap();
+
+        // Both frames of the datagram are tagged with its sender
+        let sender = sender.local_addr().unwrap().to_string();
+        for data in [[1, 2], [3, 4]] {
+            match source.next() {
+                Some(SourceEvent::Frame(frame)) => {
+                    assert_eq!(frame.data, data);
+                    assert_eq!(frame.source.as_deref(), Some(sender.as_str()));
+                }
+                event => panic!("Unexpected event {event:?}"),
+            }
+        }
+    }
+}
@@ -1,5 +1,8 @@
+use crate::decoded_entry::ArgumentValue;
+use crate::endianness::Endianness;
+use crate::target_memory::TargetMemory;
 use crate::ulog_string::ULogStringMap;
-use byteorder::{BE, ReadBytesExt};
+use byteorder::ReadBytesExt;
 use dyf::{DynDisplay, Error, FormatSpec};
 use snafu::{Backtrace, OptionExt, ResultExt, Snafu};
 use std::fmt::Debug;
@@ -19,6 +22,7 @@ pub enum ULogArgument {
     String { value: Option<String> },           //4
     Bool { value: Option<bool> },               //5
     ULogString { value: Option<Arc<String>> },  //6
+    CString { value: Option<AddressedString> }, //7
     Int8 { value: Option<i8> },                 //240
     Int16 { value: Option<i16> },               //241
     Int32 { size: usize, value: Option<i32> },  //242-243
@@ -29,6 +33,14 @@ pub enum ULogArgument {
     UInt64 { size: usize, value: Option<u64> }, //252-255
 }
 
+/// Constant string sent as its address in the memory of the target
+#[derive(Debug, Clone)]
+pub struct AddressedString {
+    pub address: u32,
+    /// The string found at the address in the ELF file, None if there is none
+    pub string: Option<String>,
+}
+
 #[derive(Snafu, Debug)]
 pub enum ULogArgumentReadError {
     #[snafu(display("The string Id is not present in the string table"))]
@@ -41,27 +53,30 @@ pub enum ULogArgumentReadError {
 }
 
 impl ULogArgument {
-    /// Populates the value field with the value from a byte stream
+    /// Populates the value field with the value from a byte stream, multi byte values being in
+    /// `endianness`. Strings sent by address are read from `memory`
     pub fn read(
         &mut self,
         reader: &mut impl BufRead,
         string_map: &ULogStringMap,
+        memory: &TargetMemory,
+        endianness: Endianness,
     ) -> Result<(), ULogArgumentReadError> {
         match self {
             // Format: (size: u32, data[size]: u8)
             ULogArgument::Slice { value } => {
-                let size = reader.read_u32::<BE>().context(IoSnafu)?;
+                let size = endianness.read_u32(reader).context(IoSnafu)?;
                 let mut data = vec![0; size as usize];
                 reader.read_exact(data.as_mut_slice()).context(IoSnafu)?;
                 *value = Some(data);
             }
             // Format: f32
             ULogArgument::Float { value } => {
-                *value = Some(reader.read_f32::<BE>().context(IoSnafu)?);
+                *value = Some(endianness.read_f32(reader).context(IoSnafu)?);
             }
             // Format: f64
             ULogArgument::Double { value } => {
-                *value = Some(reader.read_f64::<BE>().context(IoSnafu)?);
+                *value = Some(endianness.read_f64(reader).context(IoSnafu)?);
             }
             // Format: null delimited c string
             ULogArgument::String { value } => {
@@ -75,7 +90,7 @@ impl ULogArgument {
             }
             // Format: (ulog_string_id: u16)
             ULogArgument::ULogString { value } => {
-                let string_id = reader.read_u16::<BE>().context(IoSnafu)?;
+                let string_id = endianness.read_u16(reader).context(IoSnafu)?;
                 *value = Some(
                     string_map
                         .get(&string_id)
@@ -84,41 +99,30 @@ impl ULogArgument {
                         .clone(),
                 );
             }
+            // Format: (address: u32), the string is read from the ELF file. Addresses of strings
+            // that aren't constant won't be found there
+            ULogArgument::CString { value } => {
+                let address = endianness.read_u32(reader).context(IoSnafu)?;
+                *value = Some(AddressedString {
+                    address,
+                    string: memory.read_string(address.into()),
+                });
+            }
             // Format: i8
             ULogArgument::Int8 { value } => {
                 *value = Some(reader.read_i8().context(IoSnafu)?);
             }
             // Format: i16
             ULogArgument::Int16 { value } => {
-                *value = Some(reader.read_i16::<BE>().context(IoSnafu)?);
+                *value = Some(endianness.read_int(reader, 2).context(IoSnafu)? as i16);
             }
-            // Format: i24 or i32
+            // Format: i24 or i32, sign extended to an i32 since there is no actual i24
             ULogArgument::Int32 { size, value } => {
-                // We cant have an actual i24, so we pad out an i32 with 0 bytes then sign extend
-                let size = *size;
-                let mut buf = vec![0u8; size];
-                let empty_bytes = 4 - size;
-                reader
-                    .read_exact(&mut buf[empty_bytes..])
-                    .context(IoSnafu)?;
-                if buf[empty_bytes] & 0b1000_0000 > 0 {
-                    buf[0..empty_bytes].fill(0xFF);
-                }
-                *value = Some(buf.as_slice().read_i32::<BE>().context(IoSnafu)?);
+                *value = Some(endianness.read_int(reader, *size).context(IoSnafu)? as i32);
             }
             // Format: i40, i48, i56 or i64
             ULogArgument::Int64 { size, value } => {
-                // We cant have an actual i40, so we pad out an i64 with 0 bytes then sign extend
-                let size = *size;
-                let mut buf = vec![0u8; size];
-                let empty_bytes = 8 - size;
-                reader
-                    .read_exact(&mut buf[empty_bytes..])
-                    .context(IoSnafu)?;
-                if buf[empty_bytes] & 0b1000_0000 > 0 {
-                    buf[0..empty_bytes].fill(0xFF);
-                }
-                *value = Some(buf.as_slice().read_i64::<BE>().context(IoSnafu)?);
+                *value = Some(endianness.read_int(reader, *size).context(IoSnafu)?);
             }
             // Format: u8
             ULogArgument::UInt8 { value } => {
@@ -126,34 +130,143 @@ impl ULogArgument {
             }
             // Format: u16
             ULogArgument::UInt16 { value } => {
-                *value = Some(reader.read_u16::<BE>().context(IoSnafu)?);
+                *value = Some(endianness.read_u16(reader).context(IoSnafu)?);
             }
             // Format: u24 or u32
             ULogArgument::UInt32 { size, value } => {
-                // We cant have an actual u24, so we pad out a u32 with 0 bytes
-                let size = *size;
-                let mut buf = vec![0u8; size];
-                let empty_bytes = 4 - size;
-                reader
-                    .read_exact(&mut buf[empty_bytes..])
-                    .context(IoSnafu)?;
-                *value = Some(buf.as_slice().read_u32::<BE>().context(IoSnafu)?);
+                *value = Some(endianness.read_uint(reader, *size).context(IoSnafu)? as u32);
             }
             // Format: u40, u48, u56 or u64
             ULogArgument::UInt64 { size, value } => {
-                // We cant have an actual u40, so we pad out a u64 with 0 bytes
-                let size = *size;
-                let mut buf = vec![0u8; size];
-                let empty_bytes = 8 - size;
-                reader
-                    .read_exact(&mut buf[empty_bytes..])
-                    .context(IoSnafu)?;
-                *value = Some(buf.as_slice().read_u64::<BE>().context(IoSnafu)?);
+                *value = Some(endianness.read_uint(reader, *size).context(IoSnafu)?);
             }
         };
 
         Ok(())
     }
+
+    /// Gets the type id of the argument, the inverse of the conversion from a type id
+    pub fn type_id(&self) -> u8 {
+        match self {
+            ULogArgument::Slice { .. } => 1,
+            ULogArgument::Float { .. } => 2,
+            ULogArgument::Double { .. } => 3,
+            ULogArgument::String { .. } => 4,
+            ULogArgument::Bool { .. } => 5,
+            ULogArgument::ULogString { .. } => 6,
+            ULogArgument::CString { .. } => 7,
+            ULogArgument::Int8 { .. } => 240,
+            ULogArgument::Int16 { .. } => 241,
+            ULogArgument::Int32 { size, .. } | ULogArgument::Int64 { size, .. } => {
+                239 + *size as u8
+            }
+            ULogArgument::UInt8 { .. } => 248,
+            ULogArgument::UInt16 { .. } => 249,
+            ULogArgument::UInt32 { size, .. } | ULogArgument::UInt64 { size, .. } => {
+                247 + *size as u8
+            }
+        }
+    }
+
+    /// Gets the name of the argument type, integers being named after their sign and size such
+    /// as `i24` or `u8`
+    pub fn type_name(&self) -> String {
+        match self {
+            ULogArgument::Slice { .. } => "slice".to_string(),
+            ULogArgument::Float { .. } => "float".to_string(),
+            ULogArgument::Double { .. } => "double".to_string(),
+            ULogArgument::String { .. } => "string".to_string(),
+            ULogArgument::Bool { .. } => "bool".to_string(),
+            ULogArgument::ULogString { .. } => "ulog_string".to_string(),
+            ULogArgument::CString { .. } => "c_string".to_string(),
+            ULogArgument::Int8 { .. } => "i8".to_string(),
+            ULogArgument::Int16 { .. } => "i16".to_string(),
+            ULogArgument::Int32 { size, .. } | ULogArgument::Int64 { size, .. } => {
+                format!("i{}", size * 8)
+            }
+            ULogArgument::UInt8 { .. } => "u8".to_string(),
+            ULogArgument::UInt16 { .. } => "u16".to_string(),
+            ULogArgument::UInt32 { size, .. } | ULogArgument::UInt64 { size, .. } => {
+                format!("u{}", size * 8)
+            }
+        }
+    }
+
+    /// Returns the typed value of the argument, None if no value was read yet
+    pub fn value(&self) -> Option<ArgumentValue> {
+        let value = match self {
+            ULogArgument::Slice { value } => ArgumentValue::Slice(value.clone()?),
+            ULogArgument::Float { value } => ArgumentValue::Float((*value)?),
+            ULogArgument::Double { value } => ArgumentValue::Double((*value)?),
+            ULogArgument::String { value } => ArgumentValue::String(value.clone()?),
+            ULogArgument::Bool { value } => ArgumentValue::Bool((*value)?),
+            ULogArgument::ULogString { value } => ArgumentValue::ULogString(value.clone()?),
+            // Strings that weren't found are left as their address
+            ULogArgument::CString { value } => match value.clone()? {
+                AddressedString {
+                    string: Some(string),
+                    ..
+                } => ArgumentValue::String(string),
+                AddressedString { address, .. } => ArgumentValue::UInt(address.into()),
+            },
+            ULogArgument::Int8 { value } => ArgumentValue::Int((*value)?.into()),
+            ULogArgument::Int16 { value } => ArgumentValue::Int((*value)?.into()),
+            ULogArgument::Int32 { value, .. } => ArgumentValue::Int((*value)?.into()),
+            ULogArgument::Int64 { value, .. } => ArgumentValue::Int((*value)?),
+            ULogArgument::UInt8 { value } => ArgumentValue::UInt((*value)?.into()),
+            ULogArgument::UInt16 { value } => ArgumentValue::UInt((*value)?.into()),
+            ULogArgument::UInt32 { value, .. } => ArgumentValue::UInt((*value)?.into()),
+            ULogArgument::UInt64 { value, .. } => ArgumentValue::UInt((*value)?),
+        };
+        Some(value)
+    }
+
+    /// Gets a copy of the argument holding an arbitrary value of its type, to check how it
+    /// formats without reading one
+    pub fn with_sample_value(&self) -> Self {
+        use crate::ulog_argument::ULogArgument::*;
+
+        match *self {
+            Slice { .. } => Slice {
+                value: Some(vec![]),
+            },
+            Float { .. } => Float { value: Some(0.0) },
+            Double { .. } => Double { value: Some(0.0) },
+            String { .. } => String {
+                value: Some(std::string::String::new()),
+            },
+            Bool { .. } => Bool { value: Some(false) },
+            ULogString { .. } => ULogString {
+                value: Some(Arc::default()),
+            },
+            CString { .. } => CString {
+                value: Some(AddressedString {
+                    address: 0,
+                    string: Some(std::string::String::new()),
+                }),
+            },
+            Int8 { .. } => Int8 { value: Some(0) },
+            Int16 { .. } => Int16 { value: Some(0) },
+            Int32 { size, .. } => Int32 {
+                size,
+                value: Some(0),
+            },
+            Int64 { size, .. } => Int64 {
+                size,
+                value: Some(0),
+            },
+            UInt8 { .. } => UInt8 { value: Some(0) },
+            UInt16 { .. } => UInt16 { value: Some(0) },
+            UInt32 { size, .. } => UInt32 {
+                size,
+                value: Some(0),
+            },
+            UInt64 { size, .. } => UInt64 {
+                size,
+                value: Some(0),
+            },
+        }
+    }
 }
 
 #[derive(Snafu, Debug)]
@@ -175,6 +288,7 @@ impl TryFrom<u8> for ULogArgument {
             4 => String { value: None },
             5 => Bool { value: None },
             6 => ULogString { value: None },
+            7 => CString { value: None },
             240 => Int8 { value: None },
             241 => Int16 { value: None },
             // The weirder integer types are merged into the more common ones
@@ -230,6 +344,13 @@ impl DynDisplay for ULogArgument {
                 .as_ref()
                 .map(|x| x.dyn_fmt(f))
                 .unwrap_or_else(|| "(nil)".dyn_fmt(f)),
+            ULogArgument::CString { value } => value
+                .as_ref()
+                .map(|x| match &x.string {
+                    Some(string) => string.dyn_fmt(f),
+                    None => format!("(no string at {:#x})", x.address).dyn_fmt(f),
+                })
+                .unwrap_or_else(|| "(nil)".dyn_fmt(f)),
             ULogArgument::Int8 { value } => value
                 .as_ref()
                 .map(|x| x.dyn_fmt(f))
@@ -268,7 +389,11 @@ impl DynDisplay for ULogArgument {
 
 #[cfg(test)]
 mod tests {
+    use crate::decoded_entry::ArgumentValue;
+    use crate::endianness::Endianness;
+    use crate::target_memory::TargetMemory;
     use crate::ulog_argument::ULogArgument;
+    use crate::ulog_string::ULogStringMap;
     use assert_matches::assert_matches;
     use std::error::Error;
 
@@ -290,6 +415,70 @@ mod tests {
             ULogArgument::try_from(251)?,
             ULogArgument::UInt32 { size: 4, .. }
         );
+        assert_eq!(ULogArgument::try_from(242)?.type_id(), 242);
+        assert_eq!(ULogArgument::try_from(251)?.type_id(), 251);
+        assert_eq!(ULogArgument::try_from(242)?.type_name(), "i24");
+        Ok(())
+    }
+
+    #[test]
+    pub fn value() -> Result<(), Box<dyn Error>> {
+        let mut arg = ULogArgument::try_from(243)?;
+        assert_eq!(arg.value(), None);
+        arg.read(
+            &mut &[0xFF, 0xFF, 0xFF, 0xFE][..],
+            &ULogStringMap::new(),
+            &TargetMemory::default(),
+            Endianness::Big,
+        )?;
+        assert_eq!(arg.value(), Some(ArgumentValue::Int(-2)));
+        Ok(())
+    }
+
+    #[test]
+    pub fn endianness() -> Result<(), Box<dyn Error>> {
+        // i24
+        let mut arg = ULogArgument::try_from(242)?;
+        arg.read(
+            &mut &[0xFE, 0xFF, 0xFF][..],
+            &ULogStringMap::new(),
+            &TargetMemory::default(),
+            Endianness::Little,
+        )?;
+        assert_eq!(arg.value(), Some(ArgumentValue::Int(-2)));
+        // u40
+        let mut arg = ULogArgument::try_from(252)?;
+        arg.read(
+            &mut &[0x01, 0x00, 0x00, 0x00, 0x02][..],
+            &ULogStringMap::new(),
+            &TargetMemory::default(),
+            Endianness::Big,
+        )?;
+        assert_eq!(arg.value(), Some(ArgumentValue::UInt(0x01_0000_0002)));
+        Ok(())
+    }
+
+    #[test]
+    pub fn c_string() -> Result<(), Box<dyn Error>> {
+        let memory = TargetMemory::new(vec![(0x0800_1000, b"idle\x00running\x00".to_vec())]);
+        let mut arg = ULogArgument::try_from(7)?;
+        arg.read(
+            &mut &[0x08, 0x00, 0x10, 0x05][..],
+            &ULogStringMap::new(),
+            &memory,
+            Endianness::Big,
+        )?;
+        assert_eq!(
+            arg.value(),
+            Some(ArgumentValue::String("running".to_string()))
+        );
+        arg.read(
+            &mut &[0x20, 0x00, 0x00, 0x00][..],
+            &ULogStringMap::new(),
+            &memory,
+            Endianness::Big,
+        )?;
+        assert_eq!(arg.value(), Some(ArgumentValue::UInt(0x2000_0000)));
         Ok(())
     }
 
@@ -1,12 +1,18 @@
+use crate::decoded_entry::ArgumentValue;
+use crate::dwarf::{EnumType, EnumTypeMap};
+use crate::endianness::Endianness;
 use crate::location::Location;
+use crate::placeholder::{Annotation, strip_annotations};
 use crate::severity::SeverityLevel;
+use crate::symbolizer::Symbolizer;
+use crate::target_memory::TargetMemory;
 use crate::ulog_argument::{ULogArgument, ULogArgumentReadError};
 use crate::ulog_string::ULogStringMap;
-use dyf::{FormatString, Formatter};
+use dyf::{DynDisplay, FmtType, FormatSpec, FormatString, Formatter};
 use snafu::{Backtrace, ResultExt, Snafu};
-use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::BufRead;
+use std::sync::Arc;
 
 #[derive(Snafu, Debug)]
 pub enum ULogMessageFormatError {
@@ -28,8 +34,12 @@ pub enum ULogMessageFormatError {
 pub struct ULogMessage {
     /// Id of the message definition, equals the relative address to the ulog subsection start
     id: u16,
-    /// Format string for this message
+    /// Format string for this message, as written in the source
+    template: String,
+    /// Format string for this message, without the annotations
     format: FormatString,
+    /// Annotation of each placeholder of the format string
+    annotations: Vec<Option<Annotation>>,
     /// Location where this message was defined
     location: Location,
     /// Severity of this log entry
@@ -42,27 +52,31 @@ pub struct ULogMessage {
 pub type ULogMessageMap = HashMap<u16, ULogMessage>;
 
 impl ULogMessage {
+    /// Creates a message from its format string, fails if the format string is invalid
     pub fn new(
         id: u16,
-        string: FormatString,
+        template: String,
         location: Location,
         severity_level: SeverityLevel,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, dyf::Error> {
+        let (format, annotations) = strip_annotations(&template);
+        Ok(Self {
             id,
-            format: string,
+            template,
+            format: FormatString::from_string(format)?,
+            annotations,
             location,
             severity_level,
             arguments: vec![],
-        }
+        })
     }
 
     pub fn id(&self) -> u16 {
         self.id
     }
 
-    pub fn format(&self) -> Cow<'_, str> {
-        self.format.to_string_lossy()
+    pub fn format(&self) -> &str {
+        &self.template
     }
 
     pub fn location(&self) -> &Location {
@@ -81,35 +95,70 @@ impl ULogMessage {
         &mut self.arguments
     }
 
+    pub fn annotations(&self) -> &Vec<Option<Annotation>> {
+        &self.annotations
+    }
+
+    /// Resolves the debug info the annotations of the placeholders refer to
+    pub fn resolve_annotations(&mut self, enum_types: &EnumTypeMap, symbolizer: &Arc<Symbolizer>) {
+        for annotation in self.annotations.iter_mut().flatten() {
+            annotation.resolve(enum_types, symbolizer);
+        }
+    }
+
     /// Ingests the argument values from a reader into this message's arguments
     pub fn read_arguments(
         &mut self,
         reader: &mut impl BufRead,
         string_map: &ULogStringMap,
+        memory: &TargetMemory,
+        endianness: Endianness,
     ) -> Result<(), ULogArgumentReadError> {
         for argument in &mut self.arguments {
-            argument.read(reader, string_map)?;
+            argument.read(reader, string_map, memory, endianness)?;
         }
         Ok(())
     }
 
-    /// Formats this message using the values found in a reader
-    pub fn formatted_string(
+    /// Reads the values of this message's arguments from a reader into a copy of the arguments
+    pub fn read_values(
         &self,
         reader: &mut impl BufRead,
         string_map: &ULogStringMap,
-    ) -> Result<String, ULogMessageFormatError> {
+        memory: &TargetMemory,
+        endianness: Endianness,
+    ) -> Result<Vec<ULogArgument>, ULogMessageFormatError> {
         // Clone the argument list
         let mut args = self.arguments.clone();
 
         // Read values for each argument
         for (idx, argument) in args.iter_mut().enumerate() {
             argument
-                .read(reader, string_map)
+                .read(reader, string_map, memory, endianness)
                 .context(ULogArgumentReadSnafu { number: idx })?;
         }
+        Ok(args)
+    }
+
+    /// Formats this message using arguments that already have their values
+    pub fn format_values(&self, args: &[ULogArgument]) -> Result<String, ULogMessageFormatError> {
+        let args = args
+            .iter()
+            .enumerate()
+            .map(|(idx, argument)| match self.annotations.get(idx) {
+                Some(Some(Annotation::Enum { ty: Some(ty), .. })) => {
+                    DisplayArgument::Enum { argument, ty }
+                }
+                Some(Some(Annotation::Symbol {
+                    symbolizer: Some(symbolizer),
+                })) => DisplayArgument::Symbol {
+                    argument,
+                    symbolizer,
+                },
+                _ => DisplayArgument::Plain(argument),
+            })
+            .collect::<Vec<_>>();
 
-        // Format the string
         let mut template = Formatter::from(&self.format);
         for arg in &args {
             template.push_arg(arg);
@@ -117,4 +166,84 @@ impl ULogMessage {
         template.format().context(FormatSnafu)?;
         Ok(template.into_string())
     }
+
+    /// Formats this message using the values found in a reader
+    pub fn formatted_string(
+        &self,
+        reader: &mut impl BufRead,
+        string_map: &ULogStringMap,
+        memory: &TargetMemory,
+        endianness: Endianness,
+    ) -> Result<String, ULogMessageFormatError> {
+        let args = self.read_values(reader, string_map, memory, endianness)?;
+        self.format_values(&args)
+    }
+}
+
+/// Argument as displayed according to the annotation of its placeholder
+enum DisplayArgument<'a> {
+    Plain(&'a ULogArgument),
+    /// Displayed as `NAME (value)` when the value is a known enumerator
+    Enum {
+        argument: &'a ULogArgument,
+        ty: &'a EnumType,
+    },
+    /// Displayed as `symbol+offset (file:line)` when anything is known about the address
+    Symbol {
+        argument: &'a ULogArgument,
+        symbolizer: &'a Symbolizer,
+    },
+}
+
+impl DynDisplay for DisplayArgument<'_> {
+    fn dyn_fmt(&self, f: &FormatSpec) -> Result<String, dyf::Error> {
+        match self {
+            DisplayArgument::Plain(argument) => argument.dyn_fmt(f),
+            DisplayArgument::Enum { argument, ty } => {
+                let name = match argument.value() {
+                    Some(ArgumentValue::Int(x)) => ty.enumerator(x),
+                    Some(ArgumentValue::UInt(x)) => ty.enumerator(x as i64),
+                    _ => None,
+                };
+                let Some(name) = name else {
+                    return argument.dyn_fmt(f);
+                };
+                // The value keeps its formatting, the padding applies to the whole text
+                let value = argument.dyn_fmt(&FormatSpec {
+                    fill: None,
+                    align: None,
+                    zero: false,
+                    width: None,
+                    ..f.clone()
+                })?;
+                pad(format!("{name} ({value})"), f)
+            }
+            DisplayArgument::Symbol {
+                argument,
+                symbolizer,
+            } => {
+                let description = match argument.value() {
+                    Some(ArgumentValue::Int(x)) => symbolizer.describe(x as u64),
+                    Some(ArgumentValue::UInt(x)) => symbolizer.describe(x),
+                    _ => None,
+                };
+                match description {
+                    Some(description) => pad(description, f),
+                    None => argument.dyn_fmt(f),
+                }
+            }
+        }
+    }
+}
+
+/// Pads a text according to the fill, alignment and width of a format spec
+fn pad(text: String, f: &FormatSpec) -> Result<String, dyf::Error> {
+    text.dyn_fmt(&FormatSpec {
+        sign: None,
+        alternate: false,
+        zero: false,
+        precision: None,
+        ty: FmtType::Default,
+        ..f.clone()
+    })
 }
@@ -1,3 +1,6 @@
+use crate::endianness::Endianness;
+use crate::frame_crc::FrameCrc;
+use crate::target_memory::TargetMemory;
 use crate::ulog_message::ULogMessageMap;
 use crate::ulog_string::ULogStringMap;
 
@@ -10,14 +13,43 @@ pub struct ULogSystemInfo {
     messages: ULogMessageMap,
     /// System id for this system
     system_id: u16,
+    /// Optional settings declared in the meta section
+    meta: ULogSystemMeta,
+    /// Loadable sections of the elf file, holding the constant strings sent by address
+    memory: TargetMemory,
+    /// Problems found while loading the map that didn't prevent it from loading
+    warnings: Vec<String>,
+}
+
+/// Optional settings a system declares in its meta section, they all default to what the
+/// original wire format used
+#[derive(Debug, Clone, Default)]
+pub struct ULogSystemMeta {
+    /// Version of the frame header extension the system emits, 0 when it emits none
+    pub header_version: u8,
+    /// Frequency of the device tick counter in Hz, if known
+    pub tick_rate: Option<u32>,
+    /// CRC trailer appended to every frame, if any
+    pub crc: Option<FrameCrc>,
+    /// Byte order of the values the system sends, None when it doesn't declare one
+    pub endianness: Option<Endianness>,
 }
 
 impl ULogSystemInfo {
-    pub fn new(ulog_strings: ULogStringMap, messages: ULogMessageMap, system_id: u16) -> Self {
+    pub fn new(
+        ulog_strings: ULogStringMap,
+        messages: ULogMessageMap,
+        system_id: u16,
+        meta: ULogSystemMeta,
+        memory: TargetMemory,
+    ) -> Self {
         Self {
             ulog_strings,
             messages,
             system_id,
+            meta,
+            memory,
+            warnings: vec![],
         }
     }
 
@@ -32,4 +64,21 @@ impl ULogSystemInfo {
     pub fn system_id(&self) -> u16 {
         self.system_id
     }
+
+    pub fn meta(&self) -> &ULogSystemMeta {
+        &self.meta
+    }
+
+    pub fn memory(&self) -> &TargetMemory {
+        &self.memory
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Records a problem that didn't prevent the map from loading
+    pub fn add_warning(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
 }
@@ -4,3 +4,51 @@ pub fn hexdump(data: &[u8]) -> String {
     hxdmp::hexdump(data, &mut buffer).expect("Failed to hexdump");
     String::from_utf8_lossy(&buffer).to_string()
 }
+
+/// Case insensitive glob matching supporting the `*` and `?` wildcards
+pub fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let value = value.to_lowercase().chars().collect::<Vec<_>>();
+
+    // Classic greedy matching, backtracking to the last star when a mismatch happens
+    let (mut p, mut v) = (0, 0);
+    let mut last_star = None;
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                last_star = Some((p, v));
+                p += 1;
+            }
+            Some(x) if *x == '?' || *x == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match last_star {
+                // Let the star eat one more character
+                Some((star_p, star_v)) => {
+                    last_star = Some((star_p, star_v + 1));
+                    p = star_p + 1;
+                    v = star_v + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    // Only stars can match the empty rest of the value
+    pattern[p..].iter().all(|x| *x == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::glob_match;
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("*ST-Link*", "STM32 ST-LINK/V2"));
+        assert!(glob_match("ABC*", "ABC"));
+        assert!(glob_match("A?C", "abc"));
+        assert!(glob_match("*a*b", "xaxab"));
+        assert!(!glob_match("ABC*", "XABC"));
+        assert!(!glob_match("A?C", "AC"));
+    }
+}